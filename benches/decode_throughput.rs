@@ -0,0 +1,155 @@
+//! Decode throughput over a mix of typical router calldata.
+//!
+//! `cargo bench --bench decode_throughput` reports `elem/s`, i.e. decoded
+//! transactions per second on one core.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use ethers::{
+    abi::{encode, Token},
+    types::{Address, U256},
+    utils::keccak256,
+};
+use uniswap_v3_mev::mempool::{
+    contracts::ContractRegistry, intent::extract_intents, protocol::DecoderRegistry,
+};
+
+fn calldata(signature: &str, params: &[Token]) -> Vec<u8> {
+    let mut input = keccak256(signature)[..4].to_vec();
+    input.extend(encode(params));
+    input
+}
+
+fn v3_path(tokens: &[Address], fee: u32) -> Vec<u8> {
+    let mut path = tokens[0].as_bytes().to_vec();
+    for token in &tokens[1..] {
+        path.extend_from_slice(&fee.to_be_bytes()[1..]);
+        path.extend_from_slice(token.as_bytes());
+    }
+    path
+}
+
+/// `(to, input)` pairs covering each built-in decoder plus a miss.
+fn sample_transactions() -> Vec<(Address, Vec<u8>)> {
+    let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap();
+    let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+        .parse()
+        .unwrap();
+    let dai: Address = "0x6B175474E89094C44Da98b954EedeAC495271d0F"
+        .parse()
+        .unwrap();
+    let user = Address::from_low_u64_be(0xbeef);
+    let amount = Token::Uint(U256::exp10(18));
+    let deadline = Token::Uint(U256::from(1_900_000_000u64));
+
+    let universal_router: Address = "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD"
+        .parse()
+        .unwrap();
+    let swap_router_02: Address = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45"
+        .parse()
+        .unwrap();
+    let v2_router: Address = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D"
+        .parse()
+        .unwrap();
+
+    let v3_swap = encode(&[
+        Token::Address(Address::from_low_u64_be(2)),
+        amount.clone(),
+        Token::Uint(U256::zero()),
+        Token::Bytes(v3_path(&[weth, usdc, dai], 500)),
+        Token::Bool(true),
+    ]);
+    let unwrap = encode(&[Token::Address(user), Token::Uint(U256::zero())]);
+    let execute = calldata(
+        "execute(bytes,bytes[],uint256)",
+        &[
+            Token::Bytes(vec![0x00, 0x0c]),
+            Token::Array(vec![Token::Bytes(v3_swap), Token::Bytes(unwrap)]),
+            deadline.clone(),
+        ],
+    );
+
+    let exact_input_single = calldata(
+        "exactInputSingle((address,address,uint24,address,uint256,uint256,uint160))",
+        &[Token::Tuple(vec![
+            Token::Address(weth),
+            Token::Address(usdc),
+            Token::Uint(U256::from(3000)),
+            Token::Address(user),
+            amount.clone(),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+        ])],
+    );
+    let multicall = calldata(
+        "multicall(uint256,bytes[])",
+        &[
+            deadline.clone(),
+            Token::Array(vec![Token::Bytes(exact_input_single.clone())]),
+        ],
+    );
+
+    let v2_swap = calldata(
+        "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+        &[
+            amount,
+            Token::Uint(U256::zero()),
+            Token::Array(vec![Token::Address(weth), Token::Address(usdc)]),
+            Token::Address(user),
+            deadline,
+        ],
+    );
+
+    let unknown = calldata(
+        "transfer(address,uint256)",
+        &[Token::Address(user), Token::Uint(U256::one())],
+    );
+
+    vec![
+        (universal_router, execute),
+        (swap_router_02, exact_input_single),
+        (swap_router_02, multicall),
+        (v2_router, v2_swap),
+        (usdc, unknown),
+    ]
+}
+
+fn decode_throughput(c: &mut Criterion) {
+    let decoders = DecoderRegistry::with_builtin(ContractRegistry::well_known());
+    let transactions = sample_transactions();
+
+    // Builds the lazy registries outside the measurement, and makes sure the
+    // samples actually decode so we don't benchmark the miss path.
+    let decoded = transactions
+        .iter()
+        .filter(|(to, input)| matches!(decoders.decode(*to, input), Ok(Some(_))))
+        .count();
+    assert_eq!(decoded, transactions.len() - 1);
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(transactions.len() as u64));
+
+    group.bench_function("calldata", |b| {
+        b.iter(|| {
+            for (to, input) in &transactions {
+                black_box(decoders.decode(*to, black_box(input)).ok());
+            }
+        })
+    });
+
+    group.bench_function("calldata + intents", |b| {
+        b.iter(|| {
+            for (to, input) in &transactions {
+                if let Ok(Some(decoded)) = decoders.decode(*to, black_box(input)) {
+                    black_box(extract_intents(&decoded, Address::zero(), U256::zero()));
+                }
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decode_throughput);
+criterion_main!(benches);
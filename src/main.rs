@@ -3,7 +3,10 @@ use colored::Colorize;
 use log::{error, info};
 use pretty_env_logger::env_logger::fmt::Color;
 use std::io::Write;
-use uniswap_v3_mev::{mempool::listener::mempool_listener, types::settings::Settings};
+use uniswap_v3_mev::{
+    mempool::listener::{mempool_listener, replay},
+    types::settings::Settings,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -39,10 +42,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let settings = Settings::new()?;
     info!("{:#?}", settings);
 
-    let _ = match mempool_listener(settings).await {
-        Ok(_) => (),
-        Err(e) => error!("Error starting mempool listener: {}", e),
-    };
+    if settings.replay.file.is_some() {
+        if let Err(e) = replay(settings).await {
+            error!("Error replaying recorded transactions: {}", e);
+        }
+    } else if let Err(e) = mempool_listener(settings).await {
+        error!("Error starting mempool listener: {}", e);
+    }
 
     Ok(())
 }
@@ -0,0 +1,162 @@
+use std::error::Error;
+
+use crate::types::decoded::{Confidence, DecodedTransaction, Protocol};
+
+use super::{
+    decoder::{decode_abi_call, lookup},
+    path::fill_routes,
+    protocol::{ProtocolDecoder, TxContext},
+    universal_router::decode_commands,
+    utils::{
+        user_abi_labels, DODO_ROUTE_PROXY, UNISWAP_V2_ROUTER_02, UNISWAP_V3_SWAP_ROUTER,
+        UNISWAP_V3_SWAP_ROUTER_02, UNIVERSAL_ROUTER,
+    },
+};
+
+pub struct UniversalRouterDecoder;
+pub struct UniswapV3Decoder;
+pub struct UniswapV2Decoder;
+pub struct DodoDecoder;
+/// Plain ABI decoding for the ABIs loaded from the user's ABI directory.
+pub struct UserAbiDecoder;
+
+const UNIVERSAL_ROUTER_ABIS: &[&str] = &[UNIVERSAL_ROUTER];
+const UNISWAP_V3_ABIS: &[&str] = &[UNISWAP_V3_SWAP_ROUTER_02, UNISWAP_V3_SWAP_ROUTER];
+const UNISWAP_V2_ABIS: &[&str] = &[UNISWAP_V2_ROUTER_02];
+const DODO_ABIS: &[&str] = &[DODO_ROUTE_PROXY];
+
+impl ProtocolDecoder for UniversalRouterDecoder {
+    fn name(&self) -> &str {
+        "uniswap-universal-router"
+    }
+
+    fn can_decode(&self, tx: &TxContext) -> bool {
+        can_decode_with(tx, UNIVERSAL_ROUTER_ABIS)
+    }
+
+    fn decode(&self, tx: &TxContext) -> Result<DecodedTransaction, Box<dyn Error>> {
+        let mut decoded = decode_with(tx, UNIVERSAL_ROUTER_ABIS, Protocol::UniswapUniversalRouter)?;
+
+        let call = &mut decoded.call;
+        if call.function == "execute" {
+            let commands = call
+                .param("commands")
+                .and_then(|commands| commands.clone().into_bytes())
+                .unwrap_or_default();
+            let inputs: Vec<Vec<u8>> = call
+                .param("inputs")
+                .and_then(|inputs| inputs.clone().into_array())
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|input| input.into_bytes())
+                .collect();
+            call.children = decode_commands(&commands, &inputs, 1)?;
+        }
+
+        Ok(decoded)
+    }
+}
+
+impl ProtocolDecoder for UniswapV3Decoder {
+    fn name(&self) -> &str {
+        "uniswap-v3"
+    }
+
+    fn can_decode(&self, tx: &TxContext) -> bool {
+        can_decode_with(tx, UNISWAP_V3_ABIS)
+    }
+
+    fn decode(&self, tx: &TxContext) -> Result<DecodedTransaction, Box<dyn Error>> {
+        let mut decoded = decode_with(tx, UNISWAP_V3_ABIS, Protocol::UniswapV3)?;
+        fill_routes(&mut decoded.call);
+        Ok(decoded)
+    }
+}
+
+impl ProtocolDecoder for UniswapV2Decoder {
+    fn name(&self) -> &str {
+        "uniswap-v2"
+    }
+
+    fn can_decode(&self, tx: &TxContext) -> bool {
+        can_decode_with(tx, UNISWAP_V2_ABIS)
+    }
+
+    fn decode(&self, tx: &TxContext) -> Result<DecodedTransaction, Box<dyn Error>> {
+        let mut decoded = decode_with(tx, UNISWAP_V2_ABIS, Protocol::UniswapV2)?;
+        fill_routes(&mut decoded.call);
+        Ok(decoded)
+    }
+}
+
+impl ProtocolDecoder for DodoDecoder {
+    fn name(&self) -> &str {
+        "dodo"
+    }
+
+    fn can_decode(&self, tx: &TxContext) -> bool {
+        can_decode_with(tx, DODO_ABIS)
+    }
+
+    fn decode(&self, tx: &TxContext) -> Result<DecodedTransaction, Box<dyn Error>> {
+        decode_with(tx, DODO_ABIS, Protocol::Dodo)
+    }
+}
+
+impl ProtocolDecoder for UserAbiDecoder {
+    fn name(&self) -> &str {
+        "user-abis"
+    }
+
+    fn can_decode(&self, tx: &TxContext) -> bool {
+        can_decode_with(tx, &user_abi_labels())
+    }
+
+    fn decode(&self, tx: &TxContext) -> Result<DecodedTransaction, Box<dyn Error>> {
+        let labels = user_abi_labels();
+        let protocol = lookup(tx.input, Some(&scope(tx, &labels)))
+            .ok_or("Selector is not part of any user ABI")?
+            .protocol;
+        decode_with(tx, &labels, protocol)
+    }
+}
+
+/// The ABIs out of `abis` that apply to `tx`: all of them on the selector-only
+/// pass, otherwise only those the known contract is registered with.
+fn scope(tx: &TxContext, abis: &[&'static str]) -> Vec<&'static str> {
+    match tx.contract {
+        Some(contract) => abis
+            .iter()
+            .filter(|abi| contract.abis.contains(abi))
+            .copied()
+            .collect(),
+        None => abis.to_vec(),
+    }
+}
+
+fn can_decode_with(tx: &TxContext, abis: &[&'static str]) -> bool {
+    let scope = scope(tx, abis);
+    !scope.is_empty() && lookup(tx.input, Some(&scope)).is_some()
+}
+
+fn decode_with(
+    tx: &TxContext,
+    abis: &[&'static str],
+    protocol: Protocol,
+) -> Result<DecodedTransaction, Box<dyn Error>> {
+    let scope = scope(tx, abis);
+    let call = decode_abi_call(tx.input, Some(&scope))?
+        .ok_or("Selector is not part of the decoder's ABIs")?;
+
+    let confidence = match tx.contract {
+        Some(_) => Confidence::KnownContract,
+        None => Confidence::SelectorOnly,
+    };
+
+    Ok(DecodedTransaction {
+        to: tx.to,
+        protocol,
+        confidence,
+        call,
+    })
+}
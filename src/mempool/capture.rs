@@ -0,0 +1,222 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError, SyncSender, TrySendError},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use ethers::{
+    types::{Transaction, H256},
+    utils::keccak256,
+};
+use flate2::{write::GzEncoder, Compression};
+use log::{error, info};
+
+use crate::types::{recorded::RecordedTransaction, settings};
+
+use super::feed::FirstSeen;
+
+/// Records waiting to be written before new ones are dropped.
+const CAPTURE_BUFFER: usize = 65_536;
+
+/// How often the current file is flushed, so a killed listener loses at
+/// most this much.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Persists received pending transactions as gzip-compressed NDJSON of
+/// `RecordedTransaction`s, readable by replay. Writing happens on its own
+/// thread; `record` never blocks. Call `finish` before exiting, or the last
+/// file is left without its gzip trailer.
+pub struct Capture {
+    /// `None` asks the writer to finish.
+    sender: SyncSender<Option<RecordedTransaction>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+    dropped: AtomicU64,
+}
+
+impl Capture {
+    /// Starts writing to files in `[capture] directory`, creating it if needed.
+    pub fn start(settings: &settings::Capture, directory: &str) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(directory)
+            .map_err(|e| format!("Unable to create capture directory {}: {}", directory, e))?;
+
+        let mut files = CaptureFiles {
+            directory: PathBuf::from(directory),
+            rotate_bytes: settings.rotate_mb.saturating_mul(1024 * 1024),
+            rotate_after: Duration::from_secs(settings.rotate_minutes.saturating_mul(60)),
+            current: None,
+            opened: 0,
+            flushed: Instant::now(),
+        };
+        let (sender, receiver) = mpsc::sync_channel(CAPTURE_BUFFER);
+
+        let writer = thread::Builder::new()
+            .name("capture".to_string())
+            .spawn(move || loop {
+                let result = match receiver.recv_timeout(FLUSH_INTERVAL) {
+                    Ok(Some(record)) => files.write(&record),
+                    Err(RecvTimeoutError::Timeout) => files.flush(),
+                    Ok(None) | Err(RecvTimeoutError::Disconnected) => {
+                        if let Err(e) = files.finish() {
+                            error!("Unable to finish capture file: {}", e);
+                        }
+                        return;
+                    }
+                };
+                if let Err(e) = result {
+                    error!("Unable to write capture file: {}", e);
+                }
+            })?;
+
+        Ok(Capture {
+            sender,
+            writer: Mutex::new(Some(writer)),
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// Queues `transaction` for writing, as raw RLP when that reproduces its
+    /// hash and as the RPC object otherwise.
+    pub fn record(&self, transaction: &Transaction, first_seen: &FirstSeen) {
+        let raw = transaction.rlp();
+        let (raw, tx) = if transaction.hash == H256::from(keccak256(&raw)) {
+            (Some(raw), None)
+        } else {
+            (None, Some(transaction.clone()))
+        };
+
+        self.send(RecordedTransaction {
+            hash: None,
+            raw,
+            tx,
+            first_seen: first_seen.at,
+            source: Some(first_seen.endpoint.to_string()),
+        });
+    }
+
+    /// Queues a pending hash as it arrives, so it's on record even if its
+    /// body is never fetched.
+    pub fn record_hash(&self, hash: H256, first_seen: &FirstSeen) {
+        self.send(RecordedTransaction {
+            hash: Some(hash),
+            raw: None,
+            tx: None,
+            first_seen: first_seen.at,
+            source: Some(first_seen.endpoint.to_string()),
+        });
+    }
+
+    fn send(&self, record: RecordedTransaction) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(Some(record)) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes out what's queued, finishes the current file and stops the
+    /// writer, blocking until it's done. Later records are ignored.
+    pub fn finish(&self) {
+        let Some(writer) = self.writer.lock().unwrap().take() else {
+            return;
+        };
+        if self.sender.send(None).is_ok() && writer.join().is_err() {
+            error!("Capture writer panicked");
+        }
+    }
+
+    /// Transactions not captured because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+struct CaptureFile {
+    encoder: GzEncoder<BufWriter<File>>,
+    path: PathBuf,
+    written: u64,
+    opened: Instant,
+}
+
+struct CaptureFiles {
+    directory: PathBuf,
+    rotate_bytes: u64,
+    rotate_after: Duration,
+    current: Option<CaptureFile>,
+    /// Files opened so far, numbering them within the same millisecond.
+    opened: u64,
+    flushed: Instant,
+}
+
+impl CaptureFiles {
+    fn write(&mut self, record: &RecordedTransaction) -> io::Result<()> {
+        let due = self.current.as_ref().is_some_and(|file| {
+            file.written >= self.rotate_bytes || file.opened.elapsed() >= self.rotate_after
+        });
+        if due {
+            self.finish()?;
+        }
+
+        let file = match &mut self.current {
+            Some(file) => file,
+            None => {
+                self.opened += 1;
+                self.current.insert(open(&self.directory, self.opened)?)
+            }
+        };
+
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        file.encoder.write_all(&line)?;
+        file.written += line.len() as u64;
+
+        if self.flushed.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushed = Instant::now();
+        match &mut self.current {
+            Some(file) => file.encoder.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if let Some(file) = self.current.take() {
+            file.encoder.finish()?.flush()?;
+            info!(
+                "Captured {} MB of pending transactions to {}",
+                file.written / (1024 * 1024),
+                file.path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A new file named after the current time, so names sort chronologically.
+fn open(directory: &Path, sequence: u64) -> io::Result<CaptureFile> {
+    let name = format!(
+        "mempool-{}-{:04}.ndjson.gz",
+        Utc::now().format("%Y%m%d-%H%M%S%.3f"),
+        sequence
+    );
+    let path = directory.join(name);
+    let file = File::create(&path)?;
+
+    Ok(CaptureFile {
+        encoder: GzEncoder::new(BufWriter::new(file), Compression::default()),
+        path,
+        written: 0,
+        opened: Instant::now(),
+    })
+}
@@ -0,0 +1,398 @@
+use std::str::FromStr;
+
+use ethers::types::{Address, H256};
+
+use super::utils::{
+    UNISWAP_V2_ROUTER_02, UNISWAP_V3_SWAP_ROUTER, UNISWAP_V3_SWAP_ROUTER_02, UNIVERSAL_ROUTER,
+};
+
+/// Init code hash of Uniswap V2 pairs, shared by the L2 deployments and by
+/// forks that kept the pair contract unchanged (QuickSwap).
+const UNISWAP_V2_PAIR_INIT_CODE_HASH: &str =
+    "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f";
+const UNISWAP_V3_POOL_INIT_CODE_HASH: &str =
+    "0xe34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54";
+const PANCAKESWAP_V2_PAIR_INIT_CODE_HASH: &str =
+    "0x00fb7f630766e6a796048ea87d01acd3068e8ff67d078148a3fa3f4a84f69bd5";
+
+struct ProfileTable {
+    name: &'static str,
+    chain_id: u64,
+    native_symbol: &'static str,
+    native_name: &'static str,
+    wrapped_native: &'static str,
+    /// Address, name, ABI labels to decode against.
+    routers: &'static [(&'static str, &'static str, &'static [&'static str])],
+    /// Address, name, kind, init code hash.
+    factories: &'static [(&'static str, &'static str, PoolKind, &'static str)],
+}
+
+const PROFILES: &[ProfileTable] = &[
+    ProfileTable {
+        name: "mainnet",
+        chain_id: 1,
+        native_symbol: "ETH",
+        native_name: "Ether",
+        wrapped_native: "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+        routers: &[
+            (
+                "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D",
+                "Uniswap V2: Router 2",
+                &[UNISWAP_V2_ROUTER_02],
+            ),
+            (
+                "0xE592427A0AEce92De3Edee1F18E0157C05861564",
+                "Uniswap V3: Router",
+                &[UNISWAP_V3_SWAP_ROUTER],
+            ),
+            (
+                "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45",
+                "Uniswap V3: Router 2",
+                &[UNISWAP_V3_SWAP_ROUTER_02],
+            ),
+            (
+                "0xEf1c6E67703c7BD7107eed8303Fbe6EC2554BF6B",
+                "Uniswap: Universal Router (old)",
+                &[UNIVERSAL_ROUTER],
+            ),
+            (
+                "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD",
+                "Uniswap: Universal Router",
+                &[UNIVERSAL_ROUTER],
+            ),
+        ],
+        factories: &[
+            (
+                "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
+                "Uniswap V2",
+                PoolKind::V2,
+                UNISWAP_V2_PAIR_INIT_CODE_HASH,
+            ),
+            (
+                "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+                "Uniswap V3",
+                PoolKind::V3,
+                UNISWAP_V3_POOL_INIT_CODE_HASH,
+            ),
+        ],
+    },
+    ProfileTable {
+        name: "arbitrum",
+        chain_id: 42161,
+        native_symbol: "ETH",
+        native_name: "Ether",
+        wrapped_native: "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1",
+        routers: &[
+            (
+                "0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24",
+                "Uniswap V2: Router 2",
+                &[UNISWAP_V2_ROUTER_02],
+            ),
+            (
+                "0xE592427A0AEce92De3Edee1F18E0157C05861564",
+                "Uniswap V3: Router",
+                &[UNISWAP_V3_SWAP_ROUTER],
+            ),
+            (
+                "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45",
+                "Uniswap V3: Router 2",
+                &[UNISWAP_V3_SWAP_ROUTER_02],
+            ),
+            (
+                "0x5E325eDA8064b456f4781070C0738d849c824258",
+                "Uniswap: Universal Router",
+                &[UNIVERSAL_ROUTER],
+            ),
+        ],
+        factories: &[
+            (
+                "0xf1D7CC64Fb4452F05c498126312eBE29f30Fbcf9",
+                "Uniswap V2",
+                PoolKind::V2,
+                UNISWAP_V2_PAIR_INIT_CODE_HASH,
+            ),
+            (
+                "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+                "Uniswap V3",
+                PoolKind::V3,
+                UNISWAP_V3_POOL_INIT_CODE_HASH,
+            ),
+        ],
+    },
+    ProfileTable {
+        name: "base",
+        chain_id: 8453,
+        native_symbol: "ETH",
+        native_name: "Ether",
+        wrapped_native: "0x4200000000000000000000000000000000000006",
+        routers: &[
+            (
+                "0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24",
+                "Uniswap V2: Router 2",
+                &[UNISWAP_V2_ROUTER_02],
+            ),
+            (
+                "0x2626664c2603336E57B271c5C0b26F421741e481",
+                "Uniswap V3: Router 2",
+                &[UNISWAP_V3_SWAP_ROUTER_02],
+            ),
+            (
+                "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD",
+                "Uniswap: Universal Router",
+                &[UNIVERSAL_ROUTER],
+            ),
+        ],
+        factories: &[
+            (
+                "0x8909Dc15e40173Ff4699343b6eB8132c65e18eC6",
+                "Uniswap V2",
+                PoolKind::V2,
+                UNISWAP_V2_PAIR_INIT_CODE_HASH,
+            ),
+            (
+                "0x33128a8fC17869897dcE68Ed026d694621f6FDfD",
+                "Uniswap V3",
+                PoolKind::V3,
+                UNISWAP_V3_POOL_INIT_CODE_HASH,
+            ),
+        ],
+    },
+    ProfileTable {
+        name: "optimism",
+        chain_id: 10,
+        native_symbol: "ETH",
+        native_name: "Ether",
+        wrapped_native: "0x4200000000000000000000000000000000000006",
+        routers: &[
+            (
+                "0x4A7b5Da61326A6379179b40d00F57E5bbDC962c2",
+                "Uniswap V2: Router 2",
+                &[UNISWAP_V2_ROUTER_02],
+            ),
+            (
+                "0xE592427A0AEce92De3Edee1F18E0157C05861564",
+                "Uniswap V3: Router",
+                &[UNISWAP_V3_SWAP_ROUTER],
+            ),
+            (
+                "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45",
+                "Uniswap V3: Router 2",
+                &[UNISWAP_V3_SWAP_ROUTER_02],
+            ),
+            (
+                "0xCb1355ff08Ab38bBCE60111F1bb2B784bE25D7e8",
+                "Uniswap: Universal Router",
+                &[UNIVERSAL_ROUTER],
+            ),
+        ],
+        factories: &[
+            (
+                "0x0c3c1c532F1e39EdF36BE9Fe0bE1410313E074Bf",
+                "Uniswap V2",
+                PoolKind::V2,
+                UNISWAP_V2_PAIR_INIT_CODE_HASH,
+            ),
+            (
+                "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+                "Uniswap V3",
+                PoolKind::V3,
+                UNISWAP_V3_POOL_INIT_CODE_HASH,
+            ),
+        ],
+    },
+    ProfileTable {
+        name: "bsc",
+        chain_id: 56,
+        native_symbol: "BNB",
+        native_name: "BNB",
+        wrapped_native: "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c",
+        routers: &[
+            (
+                "0x10ED43C718714eb63d5aA57B78B54704E256024E",
+                "PancakeSwap V2: Router",
+                &[UNISWAP_V2_ROUTER_02],
+            ),
+            (
+                "0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24",
+                "Uniswap V2: Router 2",
+                &[UNISWAP_V2_ROUTER_02],
+            ),
+            (
+                "0xB971eF87ede563556b2ED4b1C0b0019111Dd85d2",
+                "Uniswap V3: Router 2",
+                &[UNISWAP_V3_SWAP_ROUTER_02],
+            ),
+            (
+                "0x4Dae2f939ACf50408e13d58534Ff8c2776d45265",
+                "Uniswap: Universal Router",
+                &[UNIVERSAL_ROUTER],
+            ),
+        ],
+        factories: &[
+            (
+                "0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73",
+                "PancakeSwap V2",
+                PoolKind::V2,
+                PANCAKESWAP_V2_PAIR_INIT_CODE_HASH,
+            ),
+            (
+                "0x8909Dc15e40173Ff4699343b6eB8132c65e18eC6",
+                "Uniswap V2",
+                PoolKind::V2,
+                UNISWAP_V2_PAIR_INIT_CODE_HASH,
+            ),
+            (
+                "0xdB1d10011AD0Ff90774D0C6Bb92e5C5c8b4461F7",
+                "Uniswap V3",
+                PoolKind::V3,
+                UNISWAP_V3_POOL_INIT_CODE_HASH,
+            ),
+        ],
+    },
+    ProfileTable {
+        name: "polygon",
+        chain_id: 137,
+        native_symbol: "POL",
+        native_name: "Polygon Ecosystem Token",
+        wrapped_native: "0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270",
+        routers: &[
+            (
+                "0xa5E0829CaCEd8fFDD4De3c43696c57F7D7A678ff",
+                "QuickSwap: Router",
+                &[UNISWAP_V2_ROUTER_02],
+            ),
+            (
+                "0xedf6066a2b290C185783862C7F4776A2C8077AD1",
+                "Uniswap V2: Router 2",
+                &[UNISWAP_V2_ROUTER_02],
+            ),
+            (
+                "0xE592427A0AEce92De3Edee1F18E0157C05861564",
+                "Uniswap V3: Router",
+                &[UNISWAP_V3_SWAP_ROUTER],
+            ),
+            (
+                "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45",
+                "Uniswap V3: Router 2",
+                &[UNISWAP_V3_SWAP_ROUTER_02],
+            ),
+            (
+                "0xec7BE89e9d109e7e3Fec59c222CF297125FEFda2",
+                "Uniswap: Universal Router",
+                &[UNIVERSAL_ROUTER],
+            ),
+        ],
+        factories: &[
+            (
+                "0x5757371414417b8C6CAad45bAeF941aBc7d3Ab32",
+                "QuickSwap",
+                PoolKind::V2,
+                UNISWAP_V2_PAIR_INIT_CODE_HASH,
+            ),
+            (
+                "0x9e5A52f57b3038F1B8EeE45F28b3C1967e22799C",
+                "Uniswap V2",
+                PoolKind::V2,
+                UNISWAP_V2_PAIR_INIT_CODE_HASH,
+            ),
+            (
+                "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+                "Uniswap V3",
+                PoolKind::V3,
+                UNISWAP_V3_POOL_INIT_CODE_HASH,
+            ),
+        ],
+    },
+];
+
+lazy_static::lazy_static! {
+    static ref CHAIN_PROFILES: Vec<ChainProfile> = PROFILES.iter().map(ChainProfile::parse).collect();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolKind {
+    /// Constant product pairs, salted with both tokens.
+    V2,
+    /// Concentrated liquidity pools, salted with both tokens and the fee.
+    V3,
+}
+
+#[derive(Debug, Clone)]
+pub struct Router {
+    pub address: Address,
+    pub name: &'static str,
+    /// ABI labels to decode against, tried in order.
+    pub abis: &'static [&'static str],
+}
+
+/// A pool factory and the init code hash its pools are deployed with.
+#[derive(Debug, Clone)]
+pub struct Factory {
+    pub address: Address,
+    pub name: &'static str,
+    pub kind: PoolKind,
+    pub init_code_hash: H256,
+}
+
+/// Everything chain-specific the listener needs: which node it must be
+/// talking to and which deployments it decodes.
+#[derive(Debug, Clone)]
+pub struct ChainProfile {
+    pub name: &'static str,
+    pub chain_id: u64,
+    pub native_symbol: &'static str,
+    pub native_name: &'static str,
+    pub wrapped_native: Address,
+    pub routers: Vec<Router>,
+    pub factories: Vec<Factory>,
+}
+
+impl ChainProfile {
+    fn parse(table: &ProfileTable) -> Self {
+        let address = |address: &str| Address::from_str(address).expect("Invalid profile address");
+
+        ChainProfile {
+            name: table.name,
+            chain_id: table.chain_id,
+            native_symbol: table.native_symbol,
+            native_name: table.native_name,
+            wrapped_native: address(table.wrapped_native),
+            routers: table
+                .routers
+                .iter()
+                .map(|(router, name, abis)| Router {
+                    address: address(router),
+                    name,
+                    abis,
+                })
+                .collect(),
+            factories: table
+                .factories
+                .iter()
+                .map(|(factory, name, kind, init_code_hash)| Factory {
+                    address: address(factory),
+                    name,
+                    kind: *kind,
+                    init_code_hash: H256::from_str(init_code_hash).expect("Invalid init code hash"),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Every built-in profile.
+pub fn chain_profiles() -> &'static [ChainProfile] {
+    &CHAIN_PROFILES
+}
+
+/// The profile called `name`, e.g. "mainnet" or "arbitrum".
+pub fn chain_profile(name: &str) -> Result<&'static ChainProfile, String> {
+    let name = name.to_lowercase();
+    CHAIN_PROFILES
+        .iter()
+        .find(|profile| profile.name == name)
+        .ok_or_else(|| {
+            let known: Vec<_> = CHAIN_PROFILES.iter().map(|profile| profile.name).collect();
+            format!("Unknown chain {} (known: {})", name, known.join(", "))
+        })
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ethers_providers::{Http, Ipc, IpcError, JsonRpcClient, ProviderError};
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{sync::watch, time::Instant};
+use url::Url;
+
+use crate::types::settings::Connection;
+
+/// How many of the most recent gaps `ConnectionStatus` keeps.
+const MAX_GAPS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    /// Waiting `retry_in` before reconnect attempt `attempt`.
+    Reconnecting {
+        attempt: u32,
+        retry_in: Duration,
+    },
+}
+
+impl std::fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionState::Connecting => f.write_str("connecting"),
+            ConnectionState::Connected => f.write_str("connected"),
+            ConnectionState::Reconnecting { attempt, retry_in } => write!(
+                f,
+                "reconnecting (attempt {} in {:.1}s)",
+                attempt,
+                retry_in.as_secs_f64()
+            ),
+        }
+    }
+}
+
+/// A window in which the pending transaction feed was down. Transactions
+/// first seen in it may be missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub disconnected_at: DateTime<Utc>,
+    pub reconnected_at: DateTime<Utc>,
+}
+
+impl Gap {
+    pub fn duration(&self) -> chrono::Duration {
+        self.reconnected_at - self.disconnected_at
+    }
+}
+
+/// Connection state of the listener's subscription, shared with whoever
+/// wants to watch it.
+pub struct ConnectionStatus {
+    state: watch::Sender<ConnectionState>,
+    gaps: Mutex<VecDeque<Gap>>,
+    disconnected_at: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl Default for ConnectionStatus {
+    fn default() -> Self {
+        ConnectionStatus {
+            state: watch::Sender::new(ConnectionState::Connecting),
+            gaps: Mutex::new(VecDeque::new()),
+            disconnected_at: Mutex::new(None),
+        }
+    }
+}
+
+impl ConnectionStatus {
+    pub fn state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// Notified on every state change.
+    pub fn subscribe(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
+    }
+
+    /// The most recent gaps, oldest first.
+    pub fn gaps(&self) -> Vec<Gap> {
+        self.gaps.lock().unwrap().iter().copied().collect()
+    }
+
+    pub fn set_state(&self, state: ConnectionState) {
+        self.state.send_replace(state);
+    }
+
+    /// Marks the feed as down, unless it already is.
+    pub fn disconnected(&self) {
+        self.disconnected_at
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Utc::now);
+    }
+
+    /// Marks the feed as up again and returns the gap it closes, if any.
+    pub fn connected(&self) -> Option<Gap> {
+        self.set_state(ConnectionState::Connected);
+
+        let gap = Gap {
+            disconnected_at: self.disconnected_at.lock().unwrap().take()?,
+            reconnected_at: Utc::now(),
+        };
+
+        let mut gaps = self.gaps.lock().unwrap();
+        if gaps.len() == MAX_GAPS {
+            gaps.pop_front();
+        }
+        gaps.push_back(gap);
+
+        Some(gap)
+    }
+}
+
+/// Exponential reconnect delay: `initial`, doubling up to `max`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Backoff {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Delay before the next attempt.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.attempt.min(16)))
+            .min(self.max);
+        self.attempt += 1;
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// Transport for one-off lookups: the local node's IPC socket when
+/// `ipc_path` is set, `ethereum_rpc_url` over HTTP otherwise.
+#[derive(Debug, Clone)]
+pub enum RpcClient {
+    Http(Http),
+    Ipc(Arc<IpcClient>),
+}
+
+impl RpcClient {
+    pub async fn connect(connection: &Connection) -> Result<Self, Box<dyn Error>> {
+        match &connection.ipc_path {
+            Some(path) => Ok(RpcClient::Ipc(Arc::new(
+                IpcClient::connect(path, connection).await?,
+            ))),
+            None => Ok(RpcClient::Http(Http::new(Url::parse(
+                &connection.ethereum_rpc_url,
+            )?))),
+        }
+    }
+}
+
+/// IPC lookups that reconnect when the socket goes away, e.g. because the
+/// node restarted. A request that fails that way is retried once on a new
+/// connection; while the node is down, attempts are spaced out by `Backoff`.
+#[derive(Debug)]
+pub struct IpcClient {
+    path: String,
+    state: tokio::sync::Mutex<IpcState>,
+}
+
+#[derive(Debug)]
+struct IpcState {
+    ipc: Option<Ipc>,
+    /// Bumped on every reconnect, so a failure on an old connection doesn't
+    /// drop a newer one.
+    generation: u64,
+    backoff: Backoff,
+    retry_at: Option<Instant>,
+}
+
+impl IpcClient {
+    /// Connects to the socket at `path`, failing if it isn't there.
+    pub async fn connect(path: &str, connection: &Connection) -> Result<Self, Box<dyn Error>> {
+        let ipc = Ipc::connect(path)
+            .await
+            .map_err(|e| format!("Unable to connect to {}: {}", path, e))?;
+
+        Ok(IpcClient {
+            path: path.to_string(),
+            state: tokio::sync::Mutex::new(IpcState {
+                ipc: Some(ipc),
+                generation: 0,
+                backoff: Backoff::new(
+                    Duration::from_millis(connection.reconnect_initial_ms),
+                    Duration::from_millis(connection.reconnect_max_ms),
+                ),
+                retry_at: None,
+            }),
+        })
+    }
+
+    /// The live connection, reconnecting first if the last one was lost.
+    async fn current(&self) -> Result<(u64, Ipc), IpcError> {
+        let mut state = self.state.lock().await;
+        if let Some(ipc) = &state.ipc {
+            return Ok((state.generation, ipc.clone()));
+        }
+        if state.retry_at.is_some_and(|at| Instant::now() < at) {
+            return Err(IpcError::ChannelError(format!(
+                "{} is down, waiting to reconnect",
+                self.path
+            )));
+        }
+
+        match Ipc::connect(&self.path).await {
+            Ok(ipc) => {
+                info!("{}: reconnected lookups", self.path);
+                state.backoff.reset();
+                state.retry_at = None;
+                state.generation += 1;
+                state.ipc = Some(ipc.clone());
+                Ok((state.generation, ipc))
+            }
+            Err(e) => {
+                let retry_in = state.backoff.next_delay();
+                state.retry_at = Some(Instant::now() + retry_in);
+                warn!(
+                    "{}: unable to reconnect lookups, retrying in {:.1}s: {}",
+                    self.path,
+                    retry_in.as_secs_f64(),
+                    e
+                );
+                Err(e)
+            }
+        }
+    }
+
+    async fn disconnected(&self, generation: u64) {
+        let mut state = self.state.lock().await;
+        if state.generation == generation && state.ipc.take().is_some() {
+            warn!("{}: lookup connection lost", self.path);
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for IpcClient {
+    type Error = IpcError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, IpcError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let (generation, ipc) = self.current().await?;
+        match ipc.request(method, &params).await {
+            Err(e) if is_disconnect(&e) => {
+                self.disconnected(generation).await;
+                let (_, ipc) = self.current().await?;
+                ipc.request(method, params).await
+            }
+            result => result,
+        }
+    }
+}
+
+/// Errors from the connection itself, as opposed to the node's answer.
+fn is_disconnect(error: &IpcError) -> bool {
+    !matches!(error, IpcError::JsonRpcError(_) | IpcError::JsonError(_))
+}
+
+#[async_trait]
+impl JsonRpcClient for RpcClient {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ProviderError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match self {
+            RpcClient::Http(http) => Ok(http.request(method, params).await?),
+            RpcClient::Ipc(ipc) => Ok(ipc.request(method, params).await?),
+        }
+    }
+}
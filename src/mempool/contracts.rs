@@ -0,0 +1,118 @@
+use std::{collections::HashMap, error::Error, str::FromStr};
+
+use ethers::types::Address;
+use log::warn;
+
+use crate::types::settings::Contract;
+
+use super::{
+    chains::{chain_profile, ChainProfile},
+    utils::{
+        DODO_ROUTE_PROXY, UNISWAP_V2_ROUTER_02, UNISWAP_V3_SWAP_ROUTER, UNISWAP_V3_SWAP_ROUTER_02,
+        UNIVERSAL_ROUTER,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct KnownContract {
+    pub name: String,
+    /// ABI labels to decode against, tried in order.
+    pub abis: Vec<&'static str>,
+}
+
+/// Known deployment addresses and the ABI each one should be decoded with.
+#[derive(Debug, Clone, Default)]
+pub struct ContractRegistry {
+    contracts: HashMap<Address, KnownContract>,
+}
+
+impl ContractRegistry {
+    /// Mainnet router deployments.
+    pub fn well_known() -> Self {
+        ContractRegistry::for_chain(chain_profile("mainnet").expect("Mainnet profile"))
+    }
+
+    /// The router deployments of `chain`'s profile.
+    pub fn for_chain(chain: &ChainProfile) -> Self {
+        let mut registry = ContractRegistry::default();
+
+        for router in &chain.routers {
+            registry.insert(router.address, router.name, router.abis);
+        }
+
+        registry
+    }
+
+    /// `chain`'s routers plus the addresses from `[contract]`, which are
+    /// mainnet deployments and ignored on other chains. Configured addresses
+    /// override the built-in entries.
+    pub fn from_settings(
+        contract: &Contract,
+        chain: &ChainProfile,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut registry = ContractRegistry::for_chain(chain);
+        if chain.name != "mainnet" {
+            warn!(
+                "[contract] holds mainnet addresses, ignoring it on {}",
+                chain.name
+            );
+            return Ok(registry);
+        }
+
+        let weth = Address::from_str(&contract.weth)?;
+        if weth != chain.wrapped_native {
+            return Err(format!(
+                "[contract] weth {:?} is not mainnet's wrapped Ether {:?}",
+                weth, chain.wrapped_native
+            )
+            .into());
+        }
+
+        registry.insert(
+            Address::from_str(&contract.address)?,
+            "Target pool",
+            &[DODO_ROUTE_PROXY],
+        );
+        registry.insert(
+            Address::from_str(&contract.uniswap_v2_router)?,
+            "Configured Uniswap V2 router",
+            &[UNISWAP_V2_ROUTER_02],
+        );
+
+        // `uniswap_v3_router` has been pointed at SwapRouter, SwapRouter02 and
+        // the Universal Router over time, so try all three.
+        registry.insert(
+            Address::from_str(&contract.uniswap_v3_router)?,
+            "Configured Uniswap V3 router",
+            &[
+                UNIVERSAL_ROUTER,
+                UNISWAP_V3_SWAP_ROUTER_02,
+                UNISWAP_V3_SWAP_ROUTER,
+            ],
+        );
+
+        Ok(registry)
+    }
+
+    pub fn insert(&mut self, address: Address, name: &str, abis: &[&'static str]) {
+        self.contracts.insert(
+            address,
+            KnownContract {
+                name: name.to_string(),
+                abis: abis.to_vec(),
+            },
+        );
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&KnownContract> {
+        self.contracts.get(address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.contracts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty()
+    }
+}
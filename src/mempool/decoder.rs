@@ -1,100 +1,173 @@
-use std::{error::Error, fs};
+use std::error::Error;
 
-use crate::types::logger::{log_decoded_input, token_to_string};
+use crate::types::decoded::{CallKind, DecodedCall, DecodedParam};
 
-use super::utils::UNIVERSAL_FUNCTION_MAPPING;
-use chrono::Local;
-use colored::Colorize;
-use ethabi::Contract;
-use ethers::types::Bytes;
+use super::{
+    contracts::ContractRegistry,
+    protocol::DecoderRegistry,
+    utils::{RegisteredFunction, FUNCTION_REGISTRY},
+};
+use ethabi::{ParamType, Token};
+use ethers::types::{Address, Bytes};
 
-lazy_static::lazy_static! {
-    pub static ref TARGET_POOL_ABI: String = fs::read_to_string("./Pool/WBTC-ETH.json")
-        .expect("Unable to read TARGET POOL ABI file");
+pub const TARGET_POOL_ABI: &str = include_str!("../../Pool/WBTC-ETH.json");
+pub const UNIVERSAL_ROUTER_ABI: &str = include_str!("../../uniswap/UniswapUniversal.json");
+pub const UNISWAP_V3_ROUTER_V2: &str = include_str!("../../uniswap/UniswapV3RouterRouter2.json");
 
-    pub static ref UNIVERSAL_ROUTER_ABI: String = fs::read_to_string("./uniswap/UniswapUniversal.json")
-        .expect("Unable to read Uniswap Router ABI file");
+lazy_static::lazy_static! {
+    static ref BUILTIN_DECODERS: DecoderRegistry = DecoderRegistry::with_builtin(ContractRegistry::default());
+}
 
-    pub static ref UNISWAP_V3_ROUTER_V2: String = fs::read_to_string("./uniswap/UniswapV3RouterRouter2.json")
-        .expect("Unable to read Uniswap V3 Router ABI file");
+/// Decodes `input` into a call tree by selector alone, using the built-in
+/// protocol decoders. Returns `Ok(None)` for selectors we don't know how to
+/// decode.
+pub fn input_decoder(input: Bytes) -> Result<Option<DecodedCall>, Box<dyn Error>> {
+    Ok(BUILTIN_DECODERS
+        .decode(Address::zero(), &input)?
+        .map(|decoded| decoded.call))
 }
 
-pub fn input_decoder(input: Bytes) -> Result<(), Box<dyn Error>> {
+/// Finds the function for `input`'s selector, restricted to `abis` if given.
+pub fn lookup(input: &[u8], abis: Option<&[&'static str]>) -> Option<&'static RegisteredFunction> {
     if input.len() < 4 {
-        return Ok(());
+        return None;
     }
 
-    let pool_contract = Contract::load(TARGET_POOL_ABI.as_bytes())?;
-    let uniswap_v3_router_2 = Contract::load(UNISWAP_V3_ROUTER_V2.as_bytes())?;
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&input[0..4]);
 
-    let signature = &input[0..4];
+    match abis {
+        Some(abis) => abis
+            .iter()
+            .find_map(|abi| FUNCTION_REGISTRY.get_in(abi, &selector)),
+        None => FUNCTION_REGISTRY.get(&selector),
+    }
+}
 
-    let function_name = match UNIVERSAL_FUNCTION_MAPPING.get(signature) {
-        Some(name) => name,
-        None => return Ok(()),
-    };
+/// How deep multicall / sub-plan nesting is followed before inner calls are
+/// reported as errors instead of decoded.
+pub const MAX_CALL_DEPTH: usize = 4;
+
+/// Plain ABI decoding of `input`, restricted to `abis` if given. Inner
+/// multicall payloads are delegatecalls into the same contract, so they are
+/// decoded with the same scope as the outer call. Protocol-specific structure
+/// (routes, router commands) is left to the `ProtocolDecoder`s.
+pub fn decode_abi_call(
+    input: &[u8],
+    abis: Option<&[&'static str]>,
+) -> Result<Option<DecodedCall>, Box<dyn Error>> {
+    decode_abi_call_at(input, abis, 0)
+}
 
-    let function = match *function_name {
-        "mixSwap" => pool_contract.function(function_name)?,
-        "exactInputSingle" => uniswap_v3_router_2.function(function_name)?,
-        "multicall" => uniswap_v3_router_2.function(function_name)?,
-        _ => return Ok(()),
+fn decode_abi_call_at(
+    input: &[u8],
+    abis: Option<&[&'static str]>,
+    depth: usize,
+) -> Result<Option<DecodedCall>, Box<dyn Error>> {
+    let registered = match lookup(input, abis) {
+        Some(registered) => registered,
+        None => return Ok(None),
     };
+    let selector = registered.selector;
+    let function = &registered.function;
+    let function_name = function.name.as_str();
 
     let data = &input[4..];
 
     let tokens = function.decode_input(data)?;
 
-    let types = function.inputs.clone();
-
-    if types.len() != tokens.len() {
+    if function.inputs.len() != tokens.len() {
         return Err("Mismatch between input types and decoded tokens.".into());
     }
 
-    let result = types
+    let mut children = Vec::new();
+    let params: Vec<DecodedParam> = function
+        .inputs
         .iter()
-        .zip(tokens.iter())
-        .map(|(ty, to)| {
-            let token_str = token_to_string(to);
-            if *function_name == "multicall" && ty.kind.to_string() == "bytes[]" {
-                let inner_calls: Vec<Bytes> = to
-                    .clone()
-                    .into_array()
-                    .unwrap()
-                    .into_iter()
-                    .map(|v| v.into_bytes().unwrap())
-                    .map(Bytes::from)
-                    .collect();
-
-                let timestamp = Local::now().format("%H:%M:%S%.3f").to_string();
-                let header =
-                    format!("{} ⮞ Decoded input for {}:", timestamp, function_name).bright_blue();
-                println!("\n{}", header);
-                println!("  {}", "multicall".yellow());
-
-                let mut inner_results = Vec::new();
-                for (index, inner_call) in inner_calls.into_iter().enumerate() {
-                    println!("  Call {}:", index + 1);
-                    if let Err(e) = input_decoder(inner_call.clone()) {
-                        inner_results.push(format!("    Error decoding inner call: {}", e));
+        .zip(tokens)
+        .map(|(input, token)| {
+            if function_name == "multicall" && is_bytes_array(&input.kind) {
+                if let Token::Array(inner_calls) = &token {
+                    for inner_call in inner_calls {
+                        let inner_call = match inner_call {
+                            Token::Bytes(bytes) => bytes.as_slice(),
+                            _ => &[],
+                        };
+                        children.push(decode_inner_call(inner_call, abis, depth + 1));
                     }
                 }
-                format!(
-                    "{} {}\nInner calls:\n{}",
-                    ty.kind,
-                    token_str,
-                    inner_results.join("\n")
-                )
-            } else {
-                format!("{} {}", ty.kind, token_str)
+            }
+
+            DecodedParam {
+                name: input.name.clone(),
+                kind: input.kind.clone(),
+                value: token,
             }
         })
-        .collect::<Vec<String>>()
-        .join("\n");
+        .collect();
+
+    Ok(Some(DecodedCall {
+        function: function.name.clone(),
+        signature: registered.signature.clone(),
+        kind: CallKind::Function { selector },
+        params,
+        route: Vec::new(),
+        children,
+        error: None,
+    }))
+}
 
-    if function_name != &"multicall" {
-        log_decoded_input(function_name, &result);
+fn is_bytes_array(kind: &ParamType) -> bool {
+    matches!(kind, ParamType::Array(inner) if **inner == ParamType::Bytes)
+}
+
+/// Decodes one multicall entry. Failures become an error node in the tree so
+/// a single bad inner call doesn't hide its siblings.
+fn decode_inner_call(input: &[u8], abis: Option<&[&'static str]>, depth: usize) -> DecodedCall {
+    if depth > MAX_CALL_DEPTH {
+        return failed_call(
+            input,
+            format!("Nesting deeper than {} calls", MAX_CALL_DEPTH),
+        );
+    }
+
+    match decode_abi_call_at(input, abis, depth) {
+        Ok(Some(call)) => call,
+        Ok(None) => failed_call(input, "Unknown selector".to_string()),
+        Err(e) => failed_call(input, e.to_string()),
+    }
+}
+
+/// An undecodable call: keeps the raw calldata and whatever we know about the
+/// selector next to the error.
+pub fn failed_call(input: &[u8], error: String) -> DecodedCall {
+    let mut selector = [0u8; 4];
+    if input.len() >= 4 {
+        selector.copy_from_slice(&input[0..4]);
     }
 
-    Ok(())
+    let (function, signature) = match FUNCTION_REGISTRY.get(&selector) {
+        Some(registered) => (
+            registered.function.name.clone(),
+            registered.signature.clone(),
+        ),
+        None => (
+            "unknown".to_string(),
+            format!("0x{}", hex::encode(selector)),
+        ),
+    };
+
+    DecodedCall {
+        function,
+        signature,
+        kind: CallKind::Function { selector },
+        params: vec![DecodedParam {
+            name: "data".to_string(),
+            kind: ParamType::Bytes,
+            value: Token::Bytes(input.to_vec()),
+        }],
+        route: Vec::new(),
+        children: Vec::new(),
+        error: Some(error),
+    }
 }
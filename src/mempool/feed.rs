@@ -0,0 +1,255 @@
+use std::{
+    error::Error,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, Transaction, H256, U256};
+use url::Url;
+
+use crate::types::{replacement::Replacement, settings::Dedup};
+
+use super::{connection::ConnectionStatus, filter::gas_price, pool::QueueStats, seen::SeenCache};
+
+/// Which endpoint delivered a transaction first, and when.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirstSeen {
+    pub endpoint: Arc<str>,
+    pub at: DateTime<Utc>,
+}
+
+/// How an endpoint compares to the others over the run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndpointStats {
+    /// Transactions this endpoint delivered before any other.
+    pub first: u64,
+    /// Transactions another endpoint had already delivered.
+    pub late: u64,
+    /// Summed delay behind the first endpoint over the `late` ones.
+    pub total_lag: Duration,
+}
+
+impl EndpointStats {
+    pub fn seen(&self) -> u64 {
+        self.first + self.late
+    }
+
+    pub fn average_lag(&self) -> Option<Duration> {
+        (self.late > 0).then(|| self.total_lag / self.late as u32)
+    }
+}
+
+pub struct Endpoint {
+    /// WS URL, or the path of an IPC socket.
+    pub url: String,
+    /// Host of `url`, used in logs so API keys in the path don't leak. IPC
+    /// endpoints are labelled with their path.
+    pub label: Arc<str>,
+    pub status: ConnectionStatus,
+    stats: Mutex<EndpointStats>,
+    ipc: bool,
+}
+
+impl Endpoint {
+    pub fn is_ipc(&self) -> bool {
+        self.ipc
+    }
+
+    pub fn stats(&self) -> EndpointStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+struct Seen {
+    first: FirstSeen,
+    instant: Instant,
+}
+
+/// The merged pending transaction feed of every configured endpoint.
+/// Each hash is passed on once, the first time any endpoint delivers it, and
+/// transactions reusing a sender's nonce are reported as replacements.
+pub struct Feed {
+    endpoints: Vec<Endpoint>,
+    seen: Mutex<SeenCache<H256, Seen>>,
+    /// Latest hash and gas price per `(sender, nonce)`.
+    nonces: Mutex<SeenCache<(Address, U256), (H256, U256)>>,
+    replacements: AtomicU64,
+    queue: Arc<QueueStats>,
+}
+
+impl Feed {
+    /// Fails if one of `urls` is neither a WS URL nor an IPC socket path.
+    pub fn new(urls: &[String], dedup: &Dedup) -> Result<Self, Box<dyn Error>> {
+        let ttl = Duration::from_secs(dedup.ttl_secs);
+
+        let endpoints = urls
+            .iter()
+            .enumerate()
+            .map(|(i, url)| {
+                if is_ipc(url)? {
+                    return Ok(Endpoint {
+                        url: url.clone(),
+                        label: url.as_str().into(),
+                        status: ConnectionStatus::default(),
+                        stats: Mutex::new(EndpointStats::default()),
+                        ipc: true,
+                    });
+                }
+
+                let host = Url::parse(url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_string))
+                    .unwrap_or_else(|| "endpoint".to_string());
+                let duplicate_host = urls[..i].iter().any(|other| {
+                    Url::parse(other)
+                        .ok()
+                        .is_some_and(|other| other.host_str() == Some(host.as_str()))
+                });
+
+                let label = if duplicate_host {
+                    format!("{}#{}", host, i + 1)
+                } else {
+                    host
+                };
+
+                Ok(Endpoint {
+                    url: url.clone(),
+                    label: label.into(),
+                    status: ConnectionStatus::default(),
+                    stats: Mutex::new(EndpointStats::default()),
+                    ipc: false,
+                })
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+
+        Ok(Feed {
+            endpoints,
+            seen: Mutex::new(SeenCache::new(ttl, dedup.capacity)),
+            nonces: Mutex::new(SeenCache::new(ttl, dedup.capacity)),
+            replacements: AtomicU64::new(0),
+            queue: Arc::new(QueueStats::default()),
+        })
+    }
+
+    pub fn endpoints(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+
+    /// Counters of the worker queue deduplicated transactions wait in.
+    pub fn queue(&self) -> &Arc<QueueStats> {
+        &self.queue
+    }
+
+    /// Records that `endpoint` delivered `hash`. Returns the first-seen record
+    /// if this is the first delivery, `None` for a duplicate.
+    pub fn observe(&self, endpoint: usize, hash: H256) -> Option<FirstSeen> {
+        let now = Instant::now();
+        let mut seen = self.seen.lock().unwrap();
+        seen.evict(now);
+
+        if let Some(first) = seen.get(&hash) {
+            let mut stats = self.endpoints[endpoint].stats.lock().unwrap();
+            stats.late += 1;
+            stats.total_lag += now.duration_since(first.instant);
+            return None;
+        }
+
+        let first = FirstSeen {
+            endpoint: self.endpoints[endpoint].label.clone(),
+            at: Utc::now(),
+        };
+        seen.insert(
+            hash,
+            Seen {
+                first: first.clone(),
+                instant: now,
+            },
+            now,
+        );
+        self.endpoints[endpoint].stats.lock().unwrap().first += 1;
+
+        Some(first)
+    }
+
+    /// Like `observe`, for a transaction recorded elsewhere with its own
+    /// first-seen record. Returns false for a duplicate.
+    pub fn observe_recorded(&self, hash: H256, first: FirstSeen) -> bool {
+        let now = Instant::now();
+        let mut seen = self.seen.lock().unwrap();
+        seen.evict(now);
+
+        if seen.contains(&hash) {
+            return false;
+        }
+        seen.insert(
+            hash,
+            Seen {
+                first,
+                instant: now,
+            },
+            now,
+        );
+        true
+    }
+
+    /// First-seen record of `hash`, if it's still in the dedup cache.
+    pub fn first_seen(&self, hash: &H256) -> Option<FirstSeen> {
+        self.seen
+            .lock()
+            .unwrap()
+            .get(hash)
+            .map(|seen| seen.first.clone())
+    }
+
+    /// Records `tx` under its sender and nonce. Returns the replacement if
+    /// another transaction already held that nonce.
+    pub fn record_nonce(&self, tx: &Transaction) -> Option<Replacement> {
+        let gas_price = gas_price(tx);
+        let (replaced, replaced_gas_price) = self.nonces.lock().unwrap().insert(
+            (tx.from, tx.nonce),
+            (tx.hash, gas_price),
+            Instant::now(),
+        )?;
+
+        if replaced == tx.hash {
+            return None;
+        }
+        self.replacements.fetch_add(1, Ordering::Relaxed);
+
+        Some(Replacement {
+            sender: tx.from,
+            nonce: tx.nonce,
+            replaced,
+            replaced_gas_price,
+            replacement: tx.hash,
+            gas_price,
+            at: Utc::now(),
+        })
+    }
+
+    pub fn replacements(&self) -> u64 {
+        self.replacements.load(Ordering::Relaxed)
+    }
+}
+
+/// IPC socket paths are told apart from WS URLs by having no scheme. They
+/// must also be absolute or end in `.ipc`, so a URL missing its scheme isn't
+/// taken for one.
+fn is_ipc(url: &str) -> Result<bool, String> {
+    if url.contains("://") {
+        return Ok(false);
+    }
+    if Path::new(url).is_absolute() || url.ends_with(".ipc") {
+        return Ok(true);
+    }
+
+    Err(format!(
+        "{:?} is neither a ws:// URL nor an IPC socket path (absolute, or ending in .ipc)",
+        url
+    ))
+}
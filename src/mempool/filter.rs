@@ -0,0 +1,180 @@
+use std::{collections::HashSet, error::Error, str::FromStr};
+
+use ethers::{
+    types::{Address, Transaction, U256},
+    utils::{parse_ether, parse_units},
+};
+use ethers_providers::Middleware;
+
+use crate::types::{
+    decoded::DecodedTransaction,
+    intent::{SwapAmounts, SwapIntent},
+    settings,
+};
+
+use super::tokens::TokenMetadataService;
+
+/// `[filter]` with addresses, selectors and amounts parsed once at startup.
+/// `matches_raw` runs before decoding, `matches_decoded` and
+/// `matches_notional` after.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    to: HashSet<Address>,
+    from: HashSet<Address>,
+    selectors: HashSet<[u8; 4]>,
+    protocols: HashSet<String>,
+    min_value: Option<U256>,
+    min_gas_price: Option<U256>,
+    max_gas_price: Option<U256>,
+    min_notional: Option<(Address, f64)>,
+    token_allow: HashSet<Address>,
+    token_deny: HashSet<Address>,
+}
+
+impl TransactionFilter {
+    pub fn from_settings(filter: &settings::Filter) -> Result<Self, Box<dyn Error>> {
+        let gwei =
+            |value: f64| -> Result<U256, Box<dyn Error>> { Ok(parse_units(value, "gwei")?.into()) };
+
+        Ok(TransactionFilter {
+            to: parse_addresses(&filter.to)?,
+            from: parse_addresses(&filter.from)?,
+            selectors: filter
+                .selectors
+                .iter()
+                .map(|selector| parse_selector(selector))
+                .collect::<Result<_, _>>()?,
+            protocols: filter
+                .protocols
+                .iter()
+                .map(|protocol| protocol.to_lowercase())
+                .collect(),
+            min_value: filter.min_value_eth.map(parse_ether).transpose()?,
+            min_gas_price: filter.min_gas_price_gwei.map(gwei).transpose()?,
+            max_gas_price: filter.max_gas_price_gwei.map(gwei).transpose()?,
+            min_notional: filter
+                .min_notional
+                .as_ref()
+                .map(|notional| {
+                    Address::from_str(&notional.token).map(|token| (token, notional.amount))
+                })
+                .transpose()?,
+            token_allow: parse_addresses(&filter.token_allow)?,
+            token_deny: parse_addresses(&filter.token_deny)?,
+        })
+    }
+
+    /// Checks that only need the raw transaction: addresses, top-level
+    /// selector, ETH value and gas price.
+    pub fn matches_raw(&self, tx: &Transaction) -> bool {
+        if !self.to.is_empty() && !tx.to.is_some_and(|to| self.to.contains(&to)) {
+            return false;
+        }
+        if !self.from.is_empty() && !self.from.contains(&tx.from) {
+            return false;
+        }
+        if !self.selectors.is_empty() {
+            let selector = tx.input.get(..4).and_then(|s| <[u8; 4]>::try_from(s).ok());
+            if !selector.is_some_and(|selector| self.selectors.contains(&selector)) {
+                return false;
+            }
+        }
+        if self.min_value.is_some_and(|min| tx.value < min) {
+            return false;
+        }
+
+        let gas_price = gas_price(tx);
+        if self.min_gas_price.is_some_and(|min| gas_price < min) {
+            return false;
+        }
+        if self.max_gas_price.is_some_and(|max| gas_price > max) {
+            return false;
+        }
+
+        true
+    }
+
+    /// Checks on the decoded call: protocol and the tokens the swaps touch.
+    pub fn matches_decoded(&self, decoded: &DecodedTransaction, intents: &[SwapIntent]) -> bool {
+        if !self.protocols.is_empty() && !self.protocols.contains(decoded.protocol.key()) {
+            return false;
+        }
+
+        if !self.token_deny.is_empty()
+            && intents
+                .iter()
+                .any(|intent| tokens(intent).any(|token| self.token_deny.contains(&token)))
+        {
+            return false;
+        }
+
+        if !self.token_allow.is_empty()
+            && !intents
+                .iter()
+                .any(|intent| tokens(intent).any(|token| self.token_allow.contains(&token)))
+        {
+            return false;
+        }
+
+        true
+    }
+
+    /// At least one swap moves `min_notional` or more of its token, counting
+    /// the guaranteed side (exact amounts and limits). Needs the token's
+    /// decimals, so it's checked last; without a provider they must be cached.
+    pub async fn matches_notional<M: Middleware>(
+        &self,
+        intents: &[SwapIntent],
+        tokens: &TokenMetadataService,
+        provider: Option<&M>,
+    ) -> bool {
+        let Some((token, amount)) = self.min_notional else {
+            return true;
+        };
+        let Some(metadata) = tokens.resolve(provider, token).await else {
+            return false;
+        };
+        let Ok(min) = parse_units(amount, metadata.decimals as u32) else {
+            return false;
+        };
+        let min: U256 = min.into();
+
+        intents.iter().any(|intent| {
+            let (amount_in, amount_out) = match intent.amounts {
+                SwapAmounts::ExactIn { amount_in, min_out } => (amount_in, min_out),
+                SwapAmounts::ExactOut { amount_out, max_in } => (max_in, amount_out),
+            };
+
+            (intent.token_in == token && amount_in >= min)
+                || (intent.token_out == token && amount_out >= min)
+        })
+    }
+}
+
+/// Max fee for EIP-1559 transactions, gas price for legacy ones.
+pub fn gas_price(tx: &Transaction) -> U256 {
+    tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default()
+}
+
+/// Every token a swap passes through, including intermediate hops.
+fn tokens(intent: &SwapIntent) -> impl Iterator<Item = Address> + '_ {
+    [intent.token_in, intent.token_out]
+        .into_iter()
+        .chain(intent.hops.iter().map(|hop| hop.token_out))
+}
+
+fn parse_addresses(addresses: &[String]) -> Result<HashSet<Address>, Box<dyn Error>> {
+    addresses
+        .iter()
+        .map(|address| {
+            Address::from_str(address)
+                .map_err(|e| format!("Invalid address {}: {}", address, e).into())
+        })
+        .collect()
+}
+
+fn parse_selector(selector: &str) -> Result<[u8; 4], Box<dyn Error>> {
+    let bytes = hex::decode(selector.trim_start_matches("0x"))?;
+    <[u8; 4]>::try_from(bytes.as_slice())
+        .map_err(|_| format!("Selector {} is not 4 bytes", selector).into())
+}
@@ -0,0 +1,364 @@
+use ethabi::Token;
+use ethers::types::{Address, U256};
+
+use crate::types::{
+    decoded::{CallKind, DecodedCall, DecodedTransaction, Hop, Protocol},
+    intent::{SwapAmounts, SwapIntent},
+};
+
+use super::{
+    universal_router::{V2_SWAP_EXACT_IN, V2_SWAP_EXACT_OUT, V3_SWAP_EXACT_IN, V3_SWAP_EXACT_OUT},
+    utils::NATIVE_ETH,
+};
+
+lazy_static::lazy_static! {
+    /// Router recipient sentinel meaning "send the output to the caller".
+    static ref MSG_SENDER: Address = Address::from_low_u64_be(1);
+
+    /// Router recipient sentinel meaning "keep the output in the router".
+    static ref ADDRESS_THIS: Address = Address::from_low_u64_be(2);
+}
+
+/// Extracts one intent per swap in the call tree. `sender` is the
+/// transaction's `from`, which `MSG_SENDER` recipients resolve to. `value` is
+/// the ETH sent with the transaction; it is the input amount for payable V2
+/// swaps and marks the intent as native-ETH for the V3 and Universal routers.
+pub fn extract_intents(
+    decoded: &DecodedTransaction,
+    sender: Address,
+    value: U256,
+) -> Vec<SwapIntent> {
+    let mut intents = Vec::new();
+    collect(&decoded.call, decoded.protocol, value, None, &mut intents);
+
+    let wraps_eth = !value.is_zero() || contains(&decoded.call, &["wrapETH", "WRAP_ETH"]);
+    let unwrap = find(
+        &decoded.call,
+        &["unwrapWETH9", "unwrapWETH9WithFee", "UNWRAP_WETH"],
+    );
+
+    for intent in &mut intents {
+        if wraps_eth || unwrap.is_some() {
+            intent.uses_native_eth = true;
+        }
+
+        if intent.recipient == Some(*ADDRESS_THIS) {
+            if let Some(recipient) = unwrap.and_then(unwrap_recipient) {
+                intent.recipient = Some(recipient);
+            }
+        }
+
+        if intent.recipient == Some(*MSG_SENDER) {
+            intent.recipient = Some(sender);
+        }
+    }
+
+    intents
+}
+
+fn collect(
+    call: &DecodedCall,
+    protocol: Protocol,
+    value: U256,
+    deadline: Option<U256>,
+    intents: &mut Vec<SwapIntent>,
+) {
+    if call.error.is_some() {
+        return;
+    }
+
+    // SwapRouter02 and the Universal Router put the deadline on the wrapper.
+    let deadline = uint(call, "deadline").or(deadline);
+
+    if let Some(intent) = intent_from_call(call, protocol, value, deadline) {
+        intents.push(intent);
+    }
+
+    for child in &call.children {
+        collect(child, protocol, value, deadline, intents);
+    }
+}
+
+fn intent_from_call(
+    call: &DecodedCall,
+    protocol: Protocol,
+    value: U256,
+    deadline: Option<U256>,
+) -> Option<SwapIntent> {
+    match call.kind {
+        CallKind::Command { opcode, .. } => {
+            universal_router_intent(call, opcode, protocol, deadline)
+        }
+        CallKind::Function { .. } => match call.function.as_str() {
+            "exactInputSingle" | "exactOutputSingle" => v3_single_intent(call, protocol, deadline),
+            "exactInput" | "exactOutput" => v3_path_intent(call, protocol, deadline),
+            "mixSwap" | "externalSwap" => dodo_intent(call, protocol),
+            name if name.starts_with("swap") => v2_intent(call, protocol, value, deadline),
+            _ => None,
+        },
+    }
+}
+
+fn v2_intent(
+    call: &DecodedCall,
+    protocol: Protocol,
+    value: U256,
+    deadline: Option<U256>,
+) -> Option<SwapIntent> {
+    let name = call.function.as_str();
+    let path: Vec<Address> = call
+        .param("path")?
+        .clone()
+        .into_array()?
+        .into_iter()
+        .filter_map(Token::into_address)
+        .collect();
+
+    let amounts = if name.starts_with("swapExact") {
+        SwapAmounts::ExactIn {
+            amount_in: uint(call, "amountIn").unwrap_or(value),
+            min_out: uint(call, "amountOutMin")?,
+        }
+    } else {
+        SwapAmounts::ExactOut {
+            amount_out: uint(call, "amountOut")?,
+            max_in: uint(call, "amountInMax").unwrap_or(value),
+        }
+    };
+
+    Some(SwapIntent {
+        protocol,
+        token_in: *path.first()?,
+        token_out: *path.last()?,
+        amounts,
+        recipient: address(call, "to"),
+        deadline,
+        hops: call.route.clone(),
+        uses_native_eth: name.contains("ETH"),
+    })
+}
+
+/// `ExactInputSingleParams` / `ExactOutputSingleParams`. SwapRouter has a
+/// deadline field after `recipient` that SwapRouter02 dropped.
+fn v3_single_intent(
+    call: &DecodedCall,
+    protocol: Protocol,
+    deadline: Option<U256>,
+) -> Option<SwapIntent> {
+    let fields = call.params.first()?.value.clone().into_tuple()?;
+    let offset = if fields.len() == 8 { 1 } else { 0 };
+
+    let token_in = fields.first()?.clone().into_address()?;
+    let token_out = fields.get(1)?.clone().into_address()?;
+    let fee = fields.get(2)?.clone().into_uint()?;
+    let recipient = fields.get(3)?.clone().into_address();
+    let deadline = if offset == 1 {
+        fields.get(4)?.clone().into_uint()
+    } else {
+        deadline
+    };
+    let first = fields.get(4 + offset)?.clone().into_uint()?;
+    let second = fields.get(5 + offset)?.clone().into_uint()?;
+
+    let amounts = if call.function == "exactInputSingle" {
+        SwapAmounts::ExactIn {
+            amount_in: first,
+            min_out: second,
+        }
+    } else {
+        SwapAmounts::ExactOut {
+            amount_out: first,
+            max_in: second,
+        }
+    };
+
+    Some(SwapIntent {
+        protocol,
+        token_in,
+        token_out,
+        amounts,
+        recipient,
+        deadline,
+        hops: vec![Hop {
+            token_in,
+            fee: Some(fee.low_u32()),
+            token_out,
+        }],
+        uses_native_eth: false,
+    })
+}
+
+/// `ExactInputParams` / `ExactOutputParams`; tokens come from the decoded route.
+fn v3_path_intent(
+    call: &DecodedCall,
+    protocol: Protocol,
+    deadline: Option<U256>,
+) -> Option<SwapIntent> {
+    let fields = call.params.first()?.value.clone().into_tuple()?;
+    let offset = if fields.len() == 5 { 1 } else { 0 };
+
+    let recipient = fields.get(1)?.clone().into_address();
+    let deadline = if offset == 1 {
+        fields.get(2)?.clone().into_uint()
+    } else {
+        deadline
+    };
+    let first = fields.get(2 + offset)?.clone().into_uint()?;
+    let second = fields.get(3 + offset)?.clone().into_uint()?;
+
+    let amounts = if call.function == "exactInput" {
+        SwapAmounts::ExactIn {
+            amount_in: first,
+            min_out: second,
+        }
+    } else {
+        SwapAmounts::ExactOut {
+            amount_out: first,
+            max_in: second,
+        }
+    };
+
+    Some(SwapIntent {
+        protocol,
+        token_in: call.route.first()?.token_in,
+        token_out: call.route.last()?.token_out,
+        amounts,
+        recipient,
+        deadline,
+        hops: call.route.clone(),
+        uses_native_eth: false,
+    })
+}
+
+fn universal_router_intent(
+    call: &DecodedCall,
+    opcode: u8,
+    protocol: Protocol,
+    deadline: Option<U256>,
+) -> Option<SwapIntent> {
+    let amounts = match opcode {
+        V3_SWAP_EXACT_IN | V2_SWAP_EXACT_IN => SwapAmounts::ExactIn {
+            amount_in: uint(call, "amountIn")?,
+            min_out: uint(call, "amountOutMin")?,
+        },
+        V3_SWAP_EXACT_OUT | V2_SWAP_EXACT_OUT => SwapAmounts::ExactOut {
+            amount_out: uint(call, "amountOut")?,
+            max_in: uint(call, "amountInMax")?,
+        },
+        _ => return None,
+    };
+
+    Some(SwapIntent {
+        protocol,
+        token_in: call.route.first()?.token_in,
+        token_out: call.route.last()?.token_out,
+        amounts,
+        recipient: address(call, "recipient"),
+        deadline,
+        hops: call.route.clone(),
+        uses_native_eth: false,
+    })
+}
+
+fn dodo_intent(call: &DecodedCall, protocol: Protocol) -> Option<SwapIntent> {
+    let token_in = address(call, "fromToken")?;
+    let token_out = address(call, "toToken")?;
+
+    Some(SwapIntent {
+        protocol,
+        token_in,
+        token_out,
+        amounts: SwapAmounts::ExactIn {
+            amount_in: uint(call, "fromTokenAmount")?,
+            min_out: uint(call, "minReturnAmount")?,
+        },
+        recipient: None,
+        deadline: uint(call, "deadLine"),
+        hops: Vec::new(),
+        uses_native_eth: token_in == *NATIVE_ETH || token_out == *NATIVE_ETH,
+    })
+}
+
+fn uint(call: &DecodedCall, name: &str) -> Option<U256> {
+    call.param(name).and_then(|token| token.clone().into_uint())
+}
+
+fn address(call: &DecodedCall, name: &str) -> Option<Address> {
+    call.param(name)
+        .and_then(|token| token.clone().into_address())
+}
+
+fn find<'a>(call: &'a DecodedCall, names: &[&str]) -> Option<&'a DecodedCall> {
+    if names.contains(&call.function.as_str()) && call.error.is_none() {
+        return Some(call);
+    }
+    call.children.iter().find_map(|child| find(child, names))
+}
+
+fn contains(call: &DecodedCall, names: &[&str]) -> bool {
+    find(call, names).is_some()
+}
+
+fn unwrap_recipient(call: &DecodedCall) -> Option<Address> {
+    address(call, "recipient")
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{abi::encode, utils::keccak256};
+
+    use crate::mempool::{contracts::ContractRegistry, protocol::DecoderRegistry};
+
+    use super::*;
+
+    fn execute(commands: Vec<u8>, inputs: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut input = keccak256("execute(bytes,bytes[],uint256)")[..4].to_vec();
+        input.extend(encode(&[
+            Token::Bytes(commands),
+            Token::Array(inputs.into_iter().map(Token::Bytes).collect()),
+            Token::Uint(U256::from(1_900_000_000u64)),
+        ]));
+        input
+    }
+
+    #[test]
+    fn resolves_msg_sender_recipients_to_the_transaction_sender() {
+        let (token_in, token_out) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let path = [
+            token_in.as_bytes(),
+            &[0x00, 0x01, 0xf4],
+            token_out.as_bytes(),
+        ]
+        .concat();
+        let swap = encode(&[
+            Token::Address(*MSG_SENDER),
+            Token::Uint(U256::exp10(18)),
+            Token::Uint(U256::from(1_000)),
+            Token::Bytes(path),
+            Token::Bool(true),
+        ]);
+        let decoders = DecoderRegistry::with_builtin(ContractRegistry::default());
+        let decoded = decoders
+            .decode(
+                Address::zero(),
+                &execute(vec![V3_SWAP_EXACT_IN], vec![swap]),
+            )
+            .unwrap()
+            .unwrap();
+        let sender = Address::repeat_byte(0x11);
+
+        let intents = extract_intents(&decoded, sender, U256::zero());
+
+        assert_eq!(intents.len(), 1);
+        assert_eq!(intents[0].recipient, Some(sender));
+        assert_eq!(intents[0].token_in, token_in);
+        assert_eq!(intents[0].token_out, token_out);
+        assert_eq!(
+            intents[0].amounts,
+            SwapAmounts::ExactIn {
+                amount_in: U256::exp10(18),
+                min_out: U256::from(1_000),
+            }
+        );
+    }
+}
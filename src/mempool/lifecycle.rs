@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, Block, Transaction, H256, U256};
+
+use crate::types::{
+    lifecycle::{Outcome, Resolution},
+    replacement::Replacement,
+};
+
+use super::feed::FirstSeen;
+
+/// Counts of resolved transactions over the run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LifecycleStats {
+    pub included: u64,
+    pub replaced: u64,
+    pub dropped: u64,
+    /// Summed first-seen-to-inclusion latency of the `included` ones.
+    pub total_latency: Duration,
+}
+
+impl LifecycleStats {
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.included > 0).then(|| self.total_latency / self.included as u32)
+    }
+}
+
+struct Tracked {
+    sender: Address,
+    nonce: U256,
+    first_seen: DateTime<Utc>,
+    tracked_at: Instant,
+    shown: bool,
+}
+
+#[derive(Default)]
+struct Pending {
+    by_hash: HashMap<H256, Tracked>,
+    by_nonce: HashMap<(Address, U256), H256>,
+    /// Outcomes of transactions resolved before they were shown, held until
+    /// `show` or `forget`.
+    unshown: HashMap<H256, Resolution>,
+}
+
+impl Pending {
+    fn remove(&mut self, hash: &H256) -> Option<Tracked> {
+        let tracked = self.by_hash.remove(hash)?;
+
+        // A replacement tracked since then owns the nonce now.
+        let key = (tracked.sender, tracked.nonce);
+        if self.by_nonce.get(&key) == Some(hash) {
+            self.by_nonce.remove(&key);
+        }
+        Some(tracked)
+    }
+}
+
+/// Follows decoded pending transactions until they're mined, replaced or
+/// dropped. Fed with replacements from the mempool and with new blocks.
+/// Transactions are tracked before they're shown and only counted and
+/// reported once they are, so a block arriving in between isn't missed.
+pub struct LifecycleTracker {
+    pending: Mutex<Pending>,
+    stats: Mutex<LifecycleStats>,
+    timeout: Duration,
+}
+
+impl LifecycleTracker {
+    /// Transactions still pending after `timeout` count as dropped.
+    pub fn new(timeout: Duration) -> Self {
+        LifecycleTracker {
+            pending: Mutex::new(Pending::default()),
+            stats: Mutex::new(LifecycleStats::default()),
+            timeout,
+        }
+    }
+
+    pub fn stats(&self) -> LifecycleStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Transactions waiting for an outcome.
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Starts following `transaction`. Follow with `show` once it's shown,
+    /// or `forget` if it won't be.
+    pub fn track(&self, transaction: &Transaction, first_seen: &FirstSeen) {
+        let mut pending = self.pending.lock().unwrap();
+        pending
+            .by_nonce
+            .insert((transaction.from, transaction.nonce), transaction.hash);
+        pending.by_hash.insert(
+            transaction.hash,
+            Tracked {
+                sender: transaction.from,
+                nonce: transaction.nonce,
+                first_seen: first_seen.at,
+                tracked_at: Instant::now(),
+                shown: false,
+            },
+        );
+    }
+
+    /// Counts and reports `hash` from now on. Returns its outcome if it was
+    /// resolved before it was shown.
+    pub fn show(&self, hash: &H256) -> Option<Resolution> {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(tracked) = pending.by_hash.get_mut(hash) {
+            tracked.shown = true;
+            return None;
+        }
+
+        let resolution = pending.unshown.remove(hash)?;
+        self.count(&resolution.outcome);
+        Some(resolution)
+    }
+
+    /// Stops following `hash` without counting it.
+    pub fn forget(&self, hash: &H256) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.remove(hash);
+        pending.unshown.remove(hash);
+    }
+
+    /// Resolves the replaced transaction, if it's tracked and shown.
+    pub fn replaced(&self, replacement: &Replacement) -> Option<Resolution> {
+        let mut pending = self.pending.lock().unwrap();
+        let tracked = pending.remove(&replacement.replaced)?;
+
+        self.resolve(
+            &mut pending,
+            replacement.replaced,
+            tracked,
+            Outcome::Replaced {
+                by: replacement.replacement,
+            },
+        )
+    }
+
+    /// Resolves tracked transactions mined in `block`, and those whose nonce
+    /// a different transaction in it used. `received_at` is when the block
+    /// arrived, which latencies are measured to.
+    pub fn on_block(
+        &self,
+        block: &Block<Transaction>,
+        received_at: DateTime<Utc>,
+    ) -> Vec<Resolution> {
+        let number = block.number.unwrap_or_default().as_u64();
+        let mut resolved = Vec::new();
+        let mut pending = self.pending.lock().unwrap();
+
+        for (position, transaction) in block.transactions.iter().enumerate() {
+            if let Some(tracked) = pending.remove(&transaction.hash) {
+                let latency = (received_at - tracked.first_seen)
+                    .to_std()
+                    .unwrap_or_default();
+                resolved.extend(self.resolve(
+                    &mut pending,
+                    transaction.hash,
+                    tracked,
+                    Outcome::Included {
+                        block: number,
+                        position,
+                        latency,
+                    },
+                ));
+                continue;
+            }
+
+            let holder = pending
+                .by_nonce
+                .get(&(transaction.from, transaction.nonce))
+                .copied();
+            if let Some(hash) = holder {
+                let tracked = pending.remove(&hash).expect("indexed by nonce");
+                resolved.extend(self.resolve(
+                    &mut pending,
+                    hash,
+                    tracked,
+                    Outcome::Replaced {
+                        by: transaction.hash,
+                    },
+                ));
+            }
+        }
+
+        resolved
+    }
+
+    /// Resolves transactions tracked for longer than the timeout as dropped.
+    pub fn expire(&self, now: Instant) -> Vec<Resolution> {
+        let mut pending = self.pending.lock().unwrap();
+        let expired: Vec<H256> = pending
+            .by_hash
+            .iter()
+            .filter(|(_, tracked)| now.duration_since(tracked.tracked_at) >= self.timeout)
+            .map(|(hash, _)| *hash)
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|hash| {
+                let tracked = pending.remove(&hash)?;
+                let after = now.duration_since(tracked.tracked_at);
+                self.resolve(&mut pending, hash, tracked, Outcome::Dropped { after })
+            })
+            .collect()
+    }
+
+    /// The resolution to report, or `None` if the transaction isn't shown
+    /// yet and it's held for `show` instead.
+    fn resolve(
+        &self,
+        pending: &mut Pending,
+        hash: H256,
+        tracked: Tracked,
+        outcome: Outcome,
+    ) -> Option<Resolution> {
+        let resolution = Resolution {
+            hash,
+            sender: tracked.sender,
+            nonce: tracked.nonce,
+            first_seen: tracked.first_seen,
+            outcome,
+        };
+        if !tracked.shown {
+            pending.unshown.insert(hash, resolution);
+            return None;
+        }
+
+        self.count(&resolution.outcome);
+        Some(resolution)
+    }
+
+    fn count(&self, outcome: &Outcome) {
+        let mut stats = self.stats.lock().unwrap();
+        match outcome {
+            Outcome::Included { latency, .. } => {
+                stats.included += 1;
+                stats.total_latency += *latency;
+            }
+            Outcome::Replaced { .. } => stats.replaced += 1,
+            Outcome::Dropped { .. } => stats.dropped += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(hash: u64, nonce: u64) -> Transaction {
+        Transaction {
+            hash: H256::from_low_u64_be(hash),
+            from: Address::repeat_byte(1),
+            nonce: U256::from(nonce),
+            ..Default::default()
+        }
+    }
+
+    fn track(tracker: &LifecycleTracker, transaction: &Transaction) {
+        tracker.track(
+            transaction,
+            &FirstSeen {
+                endpoint: "test".into(),
+                at: Utc::now(),
+            },
+        );
+        tracker.show(&transaction.hash);
+    }
+
+    #[test]
+    fn keeps_the_nonce_of_a_replacement_when_the_original_resolves() {
+        let tracker = LifecycleTracker::new(Duration::from_secs(60));
+        let (original, replacement) = (transaction(1, 7), transaction(2, 7));
+        track(&tracker, &original);
+        track(&tracker, &replacement);
+
+        let resolved = tracker.replaced(&Replacement {
+            sender: original.from,
+            nonce: original.nonce,
+            replaced: original.hash,
+            replaced_gas_price: U256::one(),
+            replacement: replacement.hash,
+            gas_price: U256::from(2),
+            at: Utc::now(),
+        });
+        assert!(resolved.is_some());
+
+        // A third transaction with the same nonce still displaces the
+        // replacement.
+        let cancel = transaction(3, 7);
+        let block = Block {
+            number: Some(1.into()),
+            transactions: vec![cancel.clone()],
+            ..Default::default()
+        };
+        let resolved = tracker.on_block(&block, Utc::now());
+
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].hash, replacement.hash);
+        assert_eq!(resolved[0].outcome, Outcome::Replaced { by: cancel.hash });
+        assert!(tracker.is_empty());
+    }
+}
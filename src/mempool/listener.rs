@@ -1,69 +1,889 @@
+use chrono::{DateTime, Utc};
 use colored::Colorize;
-use ethers::{
-    types::{Address, H160, U256},
-    utils::WEI_IN_ETHER,
+use ethers::types::{BlockId, BlockNumber, Transaction, H256};
+use ethers_providers::{
+    Ipc, JsonRpcError, Middleware, MiddlewareError, Provider, PubsubClient, StreamExt, Ws,
 };
-use ethers_providers::{Middleware, Provider, StreamExt, Ws};
-use log::{error, info};
-use std::time::Duration;
+use log::{debug, error, info, warn};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
 use std::{
     io::{self, Write},
-    process,
     str::FromStr,
     sync::Arc,
 };
-use url::Url;
 
-use crate::{mempool::decoder::input_decoder, types::settings::Settings};
+use crate::{
+    mempool::{
+        capture::Capture,
+        chains::{chain_profile, ChainProfile},
+        connection::{Backoff, ConnectionState, RpcClient},
+        contracts::ContractRegistry,
+        feed::{Endpoint, Feed, FirstSeen},
+        filter::{gas_price, TransactionFilter},
+        intent::extract_intents,
+        lifecycle::LifecycleTracker,
+        logs::{decode_receipt, net_token_flows},
+        pool::{spawn_workers, WorkQueue},
+        protocol::DecoderRegistry,
+        replay::{recorded_files, Pacer, RecordedReader},
+        revert::decode_revert,
+        tokens::TokenMetadataService,
+        utils::{load_user_abis, report_selector_registry},
+    },
+    types::{
+        decoded::RevertReason,
+        lifecycle::Outcome,
+        logger::{
+            log_decoded_transaction, log_execution, log_replacement, log_resolution, log_revert,
+            log_swap_intent,
+        },
+        settings::{Connection, DropPolicy, PendingTransactions, Settings},
+    },
+};
 
+/// Listens to `[connection]`'s chain and each of `chains` concurrently, each
+/// decoding the routers of its chain profile.
 pub async fn mempool_listener(config: Settings) -> Result<(), Box<dyn std::error::Error>> {
-    let wss_node_endpoint = config.connection.wss_node_endpoint;
+    load_abis(&config)?;
+
+    let mut listeners = Vec::new();
+    for (i, chain_config) in config.per_chain().into_iter().enumerate() {
+        let chain = chain_profile(&chain_config.connection.chain)?;
+        let contracts = if i == 0 {
+            ContractRegistry::from_settings(&config.contract, chain)?
+        } else {
+            ContractRegistry::for_chain(chain)
+        };
+        listeners.push(mempool_listener_with_decoders(
+            chain_config,
+            DecoderRegistry::with_builtin(contracts),
+        ));
+    }
+
+    futures::future::try_join_all(listeners).await?;
+    Ok(())
+}
 
-    let ws = Ws::connect(wss_node_endpoint).await?;
-    let url = Url::parse(&config.connection.ethereum_rpc_url).expect("Invalid URL");
-    let connection = ethers_providers::Http::new(url);
+/// Replays `[replay] file` through the same filters, decoders and output as
+/// `mempool_listener`, without a node's WS feed.
+pub async fn replay(config: Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let file = config
+        .replay
+        .file
+        .clone()
+        .ok_or("[replay] file is not set")?;
+    let decoders = builtin_decoders(&config)?;
+    replay_with_decoders(config, decoders, &file).await
+}
 
-    let provider = Arc::new(Provider::new(ws).interval(Duration::from_millis(10)));
-    let http_provider = Arc::new(Provider::new(connection).interval(Duration::from_millis(100)));
+/// Same as `replay`, but runs the transactions of `path` (a file or a
+/// directory of them) through `decoders`. They're handled one at a time, in
+/// file order, so output is reproducible.
+pub async fn replay_with_decoders(
+    config: Settings,
+    decoders: DecoderRegistry,
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let feed = Arc::new(Feed::new(&[], &config.dedup)?);
+    let queue = Arc::new(WorkQueue::new(
+        1,
+        config.workers.drop_policy,
+        Arc::clone(feed.queue()),
+    ));
+    let provider = if config.replay.offline {
+        info!("Replaying offline: token metadata comes from the cache only");
+        None
+    } else {
+        Some(connect(&config.connection).await?)
+    };
+    let handler = TransactionHandler::new(&config, provider, decoders, feed, queue, None, None)?;
 
-    let _uniswap_v3_router: Address = H160::from_str(&config.contract.uniswap_v3_router)
-        .unwrap()
-        .into();
+    let mut pacer = Pacer::new(config.replay.pacing);
+    let (mut replayed, mut skipped, mut unfetched) = (0, 0, 0);
 
-    let _value_threshold: U256 = WEI_IN_ETHER * (1 / 1000);
+    for file in recorded_files(path)? {
+        let mut reader = RecordedReader::open(&file)?;
+        let file = file.display();
+        info!("Replaying {} ({:?} pacing)", file, config.replay.pacing);
 
-    info!("Listening to Pending Transactions{}", "...".red());
+        while let Some(record) = reader.next() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    warn!("{}: {}", file, e);
+                    skipped += 1;
+                    continue;
+                }
+            };
+            if record.is_hash_only() {
+                unfetched += 1;
+                continue;
+            }
+            let transaction = match record.transaction() {
+                Ok(transaction) => transaction,
+                Err(e) => {
+                    warn!("{}: line {}: {}", file, reader.line(), e);
+                    skipped += 1;
+                    continue;
+                }
+            };
 
-    let mut stream = match provider.watch_pending_transactions().await {
-        Ok(stream) => stream,
-        Err(e) => {
-            error!("Failed to subscribe to pending transactions: {:?}", e);
-            process::exit(1);
+            pacer.wait(record.first_seen).await;
+            let first_seen = FirstSeen {
+                endpoint: record.source.as_deref().unwrap_or("replay").into(),
+                at: record.first_seen,
+            };
+            handler.replay(transaction, first_seen).await;
+            replayed += 1;
+        }
+    }
+
+    info!(
+        "Replayed {} transactions from {} ({} skipped, {} hashes without a body)",
+        replayed, path, skipped, unfetched
+    );
+    Ok(())
+}
+
+/// Provider for lookups over `[connection]`.
+async fn connect(
+    connection: &Connection,
+) -> Result<Arc<Provider<RpcClient>>, Box<dyn std::error::Error>> {
+    let client = RpcClient::connect(connection).await?;
+    Ok(Arc::new(
+        Provider::new(client).interval(Duration::from_millis(100)),
+    ))
+}
+
+/// Built-in decoders for `[connection]`'s chain and `[contract]`, after
+/// loading `[abis]`.
+fn builtin_decoders(config: &Settings) -> Result<DecoderRegistry, Box<dyn std::error::Error>> {
+    load_abis(config)?;
+
+    let chain = chain_profile(&config.connection.chain)?;
+    let contracts = ContractRegistry::from_settings(&config.contract, chain)?;
+    Ok(DecoderRegistry::with_builtin(contracts))
+}
+
+fn load_abis(config: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(directory) = &config.abis.directory {
+        let count = load_user_abis(directory)?;
+        info!("Loaded {} user ABIs from {}", count, directory);
+    }
+    Ok(())
+}
+
+/// How often per-endpoint first-seen stats are logged.
+const FEED_STATS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Same as `mempool_listener`, but runs transactions through `decoders`
+/// instead of only the built-in ones.
+pub async fn mempool_listener_with_decoders(
+    config: Settings,
+    decoders: DecoderRegistry,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let feed = Arc::new(Feed::new(
+        &config.connection.pending_endpoints(),
+        &config.dedup,
+    )?);
+    mempool_listener_with_feed(config, decoders, feed).await
+}
+
+/// Same as `mempool_listener_with_decoders`, but takes pending transactions
+/// from every endpoint of `feed`, which also reports each endpoint's
+/// connection state, feed gaps, first-seen stats and worker queue
+/// counters. Endpoints reconnect
+/// with exponential backoff whenever their subscription fails or ends;
+/// transactions already being decoded are not affected. With
+/// `[lifecycle]` enabled, shown transactions are followed into new blocks
+/// of the first endpoint.
+pub async fn mempool_listener_with_feed(
+    config: Settings,
+    decoders: DecoderRegistry,
+    feed: Arc<Feed>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let workers = &config.workers;
+    let queue = Arc::new(WorkQueue::new(
+        workers.queue_size,
+        workers.drop_policy,
+        Arc::clone(feed.queue()),
+    ));
+    let lifecycle = config.lifecycle.enabled.then(|| {
+        Arc::new(LifecycleTracker::new(Duration::from_secs(
+            config.lifecycle.drop_timeout_secs,
+        )))
+    });
+    let capture = match &config.capture.directory {
+        Some(directory) => {
+            let capture = Capture::start(&config.capture, directory)?;
+            info!("Capturing pending transactions to {}", directory);
+            Some(Arc::new(capture))
         }
+        None => None,
     };
+    let provider = connect(&config.connection).await?;
+    let handler = TransactionHandler::new(
+        &config,
+        Some(Arc::clone(&provider)),
+        decoders,
+        Arc::clone(&feed),
+        Arc::clone(&queue),
+        lifecycle.clone(),
+        capture.clone(),
+    )?;
 
-    while let Some(transaction_hash) = stream.next().await {
-        let http_provider = Arc::clone(&http_provider);
+    let chain = handler.chain;
+    let chain_id = provider.get_chainid().await?;
+    if chain_id != chain.chain_id.into() {
+        return Err(format!(
+            "Node for {} is on chain {}, expected {}",
+            chain.name, chain_id, chain.chain_id
+        )
+        .into());
+    }
+    info!(
+        "Listening on {} (chain {}, wrapped native {:?})",
+        chain.name, chain.chain_id, chain.wrapped_native
+    );
+
+    let worker = handler.clone();
+    spawn_workers(queue, workers.concurrency, move |job| {
+        let worker = worker.clone();
+        async move { worker.run(job).await }
+    });
+    info!(
+        "Decoding with {} workers, queue of {} ({:?} dropped first)",
+        workers.concurrency, workers.queue_size, workers.drop_policy
+    );
+    if workers.drop_policy == DropPolicy::LowestGasPrice
+        && config.connection.pending_transactions == PendingTransactions::Hashes
+    {
+        warn!(
+            "Pending hashes have no gas price until fetched, so the queue drops the oldest first"
+        );
+    }
+
+    let connection = Arc::new(config.connection);
+
+    let supervisors: Vec<_> = (0..feed.endpoints().len())
+        .map(|endpoint| {
+            let handler = handler.clone();
+            let connection = Arc::clone(&connection);
+            tokio::task::spawn(async move { supervise(endpoint, &connection, &handler).await })
+        })
+        .collect();
 
+    if let Some(tracker) = &lifecycle {
+        let feed = Arc::clone(&feed);
+        let connection = Arc::clone(&connection);
+        let tracker = Arc::clone(tracker);
         tokio::task::spawn(async move {
-            if let Ok(transaction_option) = http_provider.get_transaction(transaction_hash).await {
-                if let Some(transaction) = transaction_option {
-                    if let Some(_transaction_to) = transaction.to {
-                        match input_decoder(transaction.input) {
-                            Ok(s) => s,
-                            Err(_) => {
-                                return;
-                            }
-                        }
-                    }
+            watch_blocks(&feed.endpoints()[0], &connection, &tracker).await
+        });
+    }
+    tokio::task::spawn(log_feed_stats(
+        Arc::clone(&feed),
+        lifecycle,
+        capture.clone(),
+    ));
+
+    tokio::select! {
+        _ = futures::future::join_all(supervisors) => {}
+        _ = shutdown_signal() => info!("{}: shutting down", chain.name),
+    }
+
+    if let Some(capture) = capture {
+        tokio::task::spawn_blocking(move || capture.finish()).await?;
+    }
+    Ok(())
+}
+
+/// Ctrl-C, or SIGTERM on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    if let Ok(mut terminate) =
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+    {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+        return;
+    }
+
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Keeps `endpoint`'s subscription alive, reconnecting with backoff.
+async fn supervise(endpoint: usize, connection: &Connection, handler: &TransactionHandler) {
+    let source = &handler.feed.endpoints()[endpoint];
+    let status = &source.status;
+    let mut backoff = Backoff::new(
+        Duration::from_millis(connection.reconnect_initial_ms),
+        Duration::from_millis(connection.reconnect_max_ms),
+    );
+
+    loop {
+        status.set_state(ConnectionState::Connecting);
+
+        match stream_pending(endpoint, connection, handler, &mut backoff).await {
+            Ok(()) => warn!("{}: pending transaction stream ended", source.label),
+            Err(e) => error!("{}: pending transaction stream failed: {}", source.label, e),
+        }
+
+        status.disconnected();
+        let retry_in = backoff.next_delay();
+        status.set_state(ConnectionState::Reconnecting {
+            attempt: backoff.attempt(),
+            retry_in,
+        });
+        warn!("{}: {}", source.label, status.state());
+
+        tokio::time::sleep(retry_in).await;
+    }
+}
+
+/// Connects to `endpoint` over WS or IPC and feeds its pending transactions
+/// to `handler` until the subscription fails or ends.
+async fn stream_pending(
+    endpoint: usize,
+    connection: &Connection,
+    handler: &TransactionHandler,
+    backoff: &mut Backoff,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let source = &handler.feed.endpoints()[endpoint];
+
+    if source.is_ipc() {
+        let provider = Provider::new(Ipc::connect(&source.url).await?);
+        stream_pending_from(provider, endpoint, connection, handler, backoff).await
+    } else {
+        let provider = Provider::new(Ws::connect(&source.url).await?);
+        stream_pending_from(provider, endpoint, connection, handler, backoff).await
+    }
+}
+
+async fn stream_pending_from<P: PubsubClient>(
+    provider: Provider<P>,
+    endpoint: usize,
+    connection: &Connection,
+    handler: &TransactionHandler,
+    backoff: &mut Backoff,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let source = &handler.feed.endpoints()[endpoint];
+    let provider = provider.interval(Duration::from_millis(10));
+
+    if connection.pending_transactions == PendingTransactions::Full {
+        match provider
+            .subscribe::<_, Value>([json!("newPendingTransactions"), json!(true)])
+            .await
+        {
+            Ok(mut stream) => {
+                on_connected(source, backoff, "full pending transactions");
+                while let Some(item) = stream.next().await {
+                    handler.handle_pending(endpoint, item);
                 }
+                return Ok(());
             }
-        });
+            Err(e) => warn!(
+                "{}: full pending transaction subscription rejected ({}), falling back to hashes",
+                source.label, e
+            ),
+        }
+    }
+
+    let mut stream = provider.watch_pending_transactions().await?;
+    on_connected(source, backoff, "pending transaction hashes");
+
+    while let Some(transaction_hash) = stream.next().await {
+        handler.fetch_and_handle(endpoint, transaction_hash);
+    }
+
+    Ok(())
+}
+
+fn on_connected(source: &Endpoint, backoff: &mut Backoff, feed: &str) {
+    backoff.reset();
+
+    match source.status.connected() {
+        Some(gap) => warn!(
+            "{}: resubscribed to {} after a {:.1}s gap (down since {})",
+            source.label,
+            feed,
+            gap.duration().num_milliseconds() as f64 / 1000.0,
+            gap.disconnected_at.format("%H:%M:%S%.3f")
+        ),
+        None => info!("{}: listening to {}{}", source.label, feed, "...".red()),
+    }
+}
+
+/// Most blocks fetched to catch up on ones missed while resubscribing.
+const MAX_BLOCK_BACKFILL: u64 = 256;
+
+/// Resolves tracked transactions against new blocks from `source`,
+/// resubscribing with backoff like the pending transaction feed.
+async fn watch_blocks(source: &Endpoint, connection: &Connection, tracker: &LifecycleTracker) {
+    let mut backoff = Backoff::new(
+        Duration::from_millis(connection.reconnect_initial_ms),
+        Duration::from_millis(connection.reconnect_max_ms),
+    );
+    // Kept across resubscriptions, so blocks mined during a gap are fetched.
+    let mut last_block = None;
+
+    loop {
+        match stream_blocks(source, tracker, &mut backoff, &mut last_block).await {
+            Ok(()) => warn!("{}: block stream ended", source.label),
+            Err(e) => error!("{}: block stream failed: {}", source.label, e),
+        }
+
+        let retry_in = backoff.next_delay();
+        warn!(
+            "{}: resubscribing to blocks in {:.1}s",
+            source.label,
+            retry_in.as_secs_f64()
+        );
+        tokio::time::sleep(retry_in).await;
+    }
+}
+
+async fn stream_blocks(
+    source: &Endpoint,
+    tracker: &LifecycleTracker,
+    backoff: &mut Backoff,
+    last_block: &mut Option<u64>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if source.is_ipc() {
+        let provider = Provider::new(Ipc::connect(&source.url).await?);
+        stream_blocks_from(provider, source, tracker, backoff, last_block).await
+    } else {
+        let provider = Provider::new(Ws::connect(&source.url).await?);
+        stream_blocks_from(provider, source, tracker, backoff, last_block).await
+    }
+}
+
+async fn stream_blocks_from<P: PubsubClient>(
+    provider: Provider<P>,
+    source: &Endpoint,
+    tracker: &LifecycleTracker,
+    backoff: &mut Backoff,
+    last_block: &mut Option<u64>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let provider = provider.interval(Duration::from_millis(10));
+    let mut stream = provider.subscribe_blocks().await?;
+    backoff.reset();
+    info!(
+        "{}: tracking pending transactions into new blocks",
+        source.label
+    );
+
+    while let Some(header) = stream.next().await {
+        let received_at = Utc::now();
+        let Some(number) = header.number.map(|number| number.as_u64()) else {
+            continue;
+        };
+
+        if let Some(last) = *last_block {
+            let missed = number.saturating_sub(last + 1);
+            if missed > MAX_BLOCK_BACKFILL {
+                warn!(
+                    "{}: missed {} blocks, only checking the last {}",
+                    source.label, missed, MAX_BLOCK_BACKFILL
+                );
+            }
+            for missed in number.saturating_sub(missed.min(MAX_BLOCK_BACKFILL))..number {
+                resolve_block(&provider, source, tracker, missed, None).await;
+            }
+        }
+        resolve_block(&provider, source, tracker, number, Some(received_at)).await;
+        *last_block = Some(last_block.map_or(number, |last| last.max(number)));
+
+        for resolution in tracker.expire(Instant::now()) {
+            log_resolution(&resolution);
+        }
     }
 
     Ok(())
 }
 
+/// Resolves tracked transactions against block `number`. Latencies run to
+/// `received_at`, or to the block's own timestamp for blocks fetched to fill
+/// a gap, which arrived late.
+async fn resolve_block<M: Middleware>(
+    provider: &M,
+    source: &Endpoint,
+    tracker: &LifecycleTracker,
+    number: u64,
+    received_at: Option<DateTime<Utc>>,
+) {
+    let block = match provider.get_block_with_txs(number).await {
+        Ok(Some(block)) => block,
+        Ok(None) => {
+            warn!("{}: block {} not found", source.label, number);
+            return;
+        }
+        Err(e) => {
+            warn!("{}: unable to fetch block {}: {}", source.label, number, e);
+            return;
+        }
+    };
+    let received_at = received_at.unwrap_or_else(|| {
+        DateTime::from_timestamp(block.timestamp.low_u64() as i64, 0).unwrap_or_else(Utc::now)
+    });
+
+    for resolution in tracker.on_block(&block, received_at) {
+        log_resolution(&resolution);
+        if let Outcome::Included { position, .. } = resolution.outcome {
+            report_execution(provider, &block.transactions[position]).await;
+        }
+    }
+}
+
+/// Logs what an included transaction did: the sender's net token flows from
+/// its receipt, or why it reverted.
+async fn report_execution<M: Middleware>(provider: &M, transaction: &Transaction) {
+    let receipt = match provider.get_transaction_receipt(transaction.hash).await {
+        Ok(Some(receipt)) => receipt,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Unable to fetch receipt of {:?}: {}", transaction.hash, e);
+            return;
+        }
+    };
+
+    if receipt.status == Some(0.into()) {
+        let reason = replay_revert(provider, transaction).await;
+        log_revert(transaction.hash, reason.as_ref());
+        return;
+    }
+
+    let logs = decode_receipt(&receipt);
+    log_execution(transaction.hash, &net_token_flows(&logs, transaction.from));
+}
+
+/// Re-runs a failed transaction with `eth_call` on its parent block to get
+/// the revert data, which receipts don't carry. Transactions earlier in the
+/// same block aren't applied, so the call may not fail the same way.
+async fn replay_revert<M: Middleware>(
+    provider: &M,
+    transaction: &Transaction,
+) -> Option<RevertReason> {
+    let parent = transaction.block_number?.low_u64().checked_sub(1)?;
+    let block = BlockId::Number(BlockNumber::Number(parent.into()));
+
+    match provider.call(&transaction.into(), Some(block)).await {
+        Ok(_) => None,
+        Err(e) => {
+            let data = e
+                .as_error_response()
+                .and_then(JsonRpcError::as_revert_data)?;
+            Some(decode_revert(&data))
+        }
+    }
+}
+
+async fn log_feed_stats(
+    feed: Arc<Feed>,
+    lifecycle: Option<Arc<LifecycleTracker>>,
+    capture: Option<Arc<Capture>>,
+) {
+    let mut interval = tokio::time::interval(FEED_STATS_INTERVAL);
+    interval.tick().await;
+    let mut last_dropped = 0;
+    let mut last_replacements = 0;
+    let mut last_uncaptured = 0;
+
+    loop {
+        interval.tick().await;
+
+        let queue = feed.queue();
+        let dropped = queue.dropped();
+        if dropped > last_dropped {
+            warn!(
+                "Falling behind: dropped {} queued transactions in the last {}s ({} total, {} in backlog)",
+                dropped - last_dropped,
+                FEED_STATS_INTERVAL.as_secs(),
+                dropped,
+                queue.backlog()
+            );
+        }
+        last_dropped = dropped;
+
+        let replacements = feed.replacements();
+        if replacements > last_replacements {
+            debug!(
+                "{} nonce replacements in the last {}s",
+                replacements - last_replacements,
+                FEED_STATS_INTERVAL.as_secs()
+            );
+        }
+        last_replacements = replacements;
+
+        if let Some(capture) = &capture {
+            let uncaptured = capture.dropped();
+            if uncaptured > last_uncaptured {
+                warn!(
+                    "Capture falling behind: {} transactions not written in the last {}s",
+                    uncaptured - last_uncaptured,
+                    FEED_STATS_INTERVAL.as_secs()
+                );
+            }
+            last_uncaptured = uncaptured;
+        }
+
+        if let Some(tracker) = &lifecycle {
+            let stats = tracker.stats();
+            info!(
+                "Lifecycle: {} included (avg {}ms after first seen), {} replaced, {} dropped, {} pending",
+                stats.included,
+                stats.average_latency().map_or(0, |latency| latency.as_millis()),
+                stats.replaced,
+                stats.dropped,
+                tracker.len()
+            );
+        }
+
+        if feed.endpoints().len() < 2 {
+            continue;
+        }
+        for source in feed.endpoints() {
+            let stats = source.stats();
+            let first_share = match stats.seen() {
+                0 => 0.0,
+                seen => stats.first as f64 * 100.0 / seen as f64,
+            };
+            info!(
+                "{}: {} seen, first for {} ({:.1}%), avg lag {}ms",
+                source.label,
+                stats.seen(),
+                stats.first,
+                first_share,
+                stats.average_lag().map_or(0, |lag| lag.as_millis())
+            );
+        }
+    }
+}
+
+/// A deduplicated pending transaction waiting for a worker.
+enum Job {
+    /// Only the hash is known; the body still has to be fetched.
+    Fetch(H256, FirstSeen),
+    Process(Box<Transaction>, FirstSeen),
+}
+
+/// What every pending transaction is run through, shared by the spawned
+/// per-transaction tasks.
+#[derive(Clone)]
+struct TransactionHandler {
+    /// Lookups go over IPC when it's configured, HTTP otherwise. `None` when
+    /// replaying offline.
+    provider: Option<Arc<Provider<RpcClient>>>,
+    chain: &'static ChainProfile,
+    /// Shown with each transaction when listening to several chains.
+    chain_label: Option<&'static str>,
+    decoders: Arc<DecoderRegistry>,
+    tokens: Arc<TokenMetadataService>,
+    feed: Arc<Feed>,
+    queue: Arc<WorkQueue<Job>>,
+    filter: Arc<TransactionFilter>,
+    lifecycle: Option<Arc<LifecycleTracker>>,
+    capture: Option<Arc<Capture>>,
+}
+
+impl TransactionHandler {
+    fn new(
+        config: &Settings,
+        provider: Option<Arc<Provider<RpcClient>>>,
+        decoders: DecoderRegistry,
+        feed: Arc<Feed>,
+        queue: Arc<WorkQueue<Job>>,
+        lifecycle: Option<Arc<LifecycleTracker>>,
+        capture: Option<Arc<Capture>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let chain = chain_profile(&config.connection.chain)?;
+        let filter = Arc::new(TransactionFilter::from_settings(&config.filter)?);
+
+        report_selector_registry();
+
+        let decoders = Arc::new(decoders);
+        let tokens = Arc::new(TokenMetadataService::load(
+            config.tokens.cache_path_for(chain.name),
+            chain,
+        )?);
+        info!(
+            "Tracking {} known contracts with decoders: {}",
+            decoders.contracts().len(),
+            decoders.names().join(", ")
+        );
+
+        Ok(TransactionHandler {
+            provider,
+            chain,
+            chain_label: (!config.chains.is_empty()).then_some(chain.name),
+            decoders,
+            tokens,
+            feed,
+            queue,
+            filter,
+            lifecycle,
+            capture,
+        })
+    }
+
+    /// One item of a `newPendingTransactions` subscription with full bodies.
+    /// Nodes that ignore the flag still send bare hashes, so those are
+    /// fetched like on the fallback path.
+    fn handle_pending(&self, endpoint: usize, item: Value) {
+        if let Some(hash) = item.as_str() {
+            if let Ok(hash) = H256::from_str(hash) {
+                self.fetch_and_handle(endpoint, hash);
+            }
+            return;
+        }
+
+        match serde_json::from_value::<Transaction>(item) {
+            Ok(transaction) => self.handle(endpoint, transaction),
+            Err(e) => warn!("Unable to parse pending transaction: {}", e),
+        }
+    }
+
+    /// Deduplicates before fetching, so a hash every endpoint announces is
+    /// only looked up once.
+    fn fetch_and_handle(&self, endpoint: usize, transaction_hash: H256) {
+        if let Some(first_seen) = self.feed.observe(endpoint, transaction_hash) {
+            if let Some(capture) = &self.capture {
+                capture.record_hash(transaction_hash, &first_seen);
+            }
+            self.queue
+                .push(None, Job::Fetch(transaction_hash, first_seen));
+        }
+    }
+
+    fn handle(&self, endpoint: usize, transaction: Transaction) {
+        let Some(first_seen) = self.feed.observe(endpoint, transaction.hash) else {
+            return;
+        };
+        self.capture(&transaction, &first_seen);
+
+        // Full bodies can be filtered before they take up queue space.
+        if self.matches_raw(&transaction) {
+            self.queue.push(
+                Some(gas_price(&transaction)),
+                Job::Process(Box::new(transaction), first_seen),
+            );
+        }
+    }
+
+    async fn run(&self, job: Job) {
+        match job {
+            Job::Fetch(transaction_hash, first_seen) => {
+                let Some(provider) = &self.provider else {
+                    return;
+                };
+                if let Ok(Some(transaction)) = provider.get_transaction(transaction_hash).await {
+                    self.capture(&transaction, &first_seen);
+                    if self.matches_raw(&transaction) {
+                        self.process(transaction, first_seen).await;
+                    }
+                }
+            }
+            Job::Process(transaction, first_seen) => self.process(*transaction, first_seen).await,
+        }
+    }
+
+    /// Captured before any filtering, so captures can be re-decoded with
+    /// other filters later.
+    fn capture(&self, transaction: &Transaction, first_seen: &FirstSeen) {
+        if let Some(capture) = &self.capture {
+            capture.record(transaction, first_seen);
+        }
+    }
+
+    /// Raw filter, also reporting the transaction if it replaces another
+    /// one's nonce. Replacements are only noted once the body is known.
+    fn matches_raw(&self, transaction: &Transaction) -> bool {
+        let replacement = self.feed.record_nonce(transaction);
+        if let (Some(replacement), Some(tracker)) = (&replacement, &self.lifecycle) {
+            if let Some(resolution) = tracker.replaced(replacement) {
+                log_resolution(&resolution);
+            }
+        }
+        if !self.filter.matches_raw(transaction) {
+            return false;
+        }
+
+        if let Some(replacement) = replacement {
+            log_replacement(&replacement);
+        }
+        true
+    }
+
+    /// A recorded transaction, processed right away instead of queued.
+    async fn replay(&self, transaction: Transaction, first_seen: FirstSeen) {
+        if self
+            .feed
+            .observe_recorded(transaction.hash, first_seen.clone())
+            && self.matches_raw(&transaction)
+        {
+            self.process(transaction, first_seen).await;
+        }
+    }
+
+    async fn process(&self, transaction: Transaction, first_seen: FirstSeen) {
+        let Some(transaction_to) = transaction.to else {
+            return;
+        };
+        let Some(decoded) = self
+            .decoders
+            .decode(transaction_to, &transaction.input)
+            .ok()
+            .flatten()
+        else {
+            return;
+        };
+
+        let intents = extract_intents(&decoded, transaction.from, transaction.value);
+        if !self.filter.matches_decoded(&decoded, &intents) {
+            return;
+        }
+
+        // Tracked before the token lookups below, which can take longer than
+        // it takes the transaction to be mined.
+        if let Some(tracker) = &self.lifecycle {
+            tracker.track(&transaction, &first_seen);
+        }
+        if !self
+            .filter
+            .matches_notional(&intents, &self.tokens, self.provider.as_deref())
+            .await
+        {
+            if let Some(tracker) = &self.lifecycle {
+                tracker.forget(&transaction.hash);
+            }
+            return;
+        }
+
+        log_decoded_transaction(&decoded, self.chain_label);
+        debug!(
+            "{:?} first seen by {} at {}",
+            transaction.hash,
+            first_seen.endpoint,
+            first_seen.at.format("%H:%M:%S%.3f")
+        );
+        for intent in intents {
+            let provider = self.provider.as_deref();
+            let token_in = self.tokens.resolve(provider, intent.token_in).await;
+            let token_out = self.tokens.resolve(provider, intent.token_out).await;
+            log_swap_intent(&intent, token_in.as_ref(), token_out.as_ref());
+        }
+
+        if let Some(tracker) = &self.lifecycle {
+            if let Some(resolution) = tracker.show(&transaction.hash) {
+                log_resolution(&resolution);
+            }
+        }
+    }
+}
+
 pub fn clear_previous_line() -> io::Result<()> {
     let clear_line = "\x1b[1A\x1b[2K";
     io::stdout().write_all(clear_line.as_bytes())?;
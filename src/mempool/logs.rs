@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use ethabi::{Contract, Event, RawLog, Token};
+use ethers::{
+    abi::parse_abi,
+    types::{Address, Log, TransactionReceipt, H256, I256, U256},
+};
+
+use crate::types::decoded::{DecodedEvent, DecodedLog};
+
+use super::utils::{parsed_abi, DODO_ROUTE_PROXY, WETH_ABI};
+
+/// Pool and token events that aren't in any of the bundled JSON ABIs.
+const POOL_EVENTS: &[&str] = &[
+    "event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)",
+    "event Sync(uint112 reserve0, uint112 reserve1)",
+    "event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick)",
+    "event Transfer(address indexed from, address indexed to, uint256 value)",
+    "event Approval(address indexed owner, address indexed spender, uint256 value)",
+    "event DODOSwap(address fromToken, address toToken, uint256 fromAmount, uint256 toAmount, address trader, address receiver)",
+];
+
+lazy_static::lazy_static! {
+    /// Every event we can decode, keyed by topic0. WETH `Deposit`/`Withdrawal`
+    /// and DODO `OrderHistory` come from the bundled ABIs.
+    static ref EVENT_REGISTRY: HashMap<H256, Vec<Event>> = {
+        let mut m: HashMap<H256, Vec<Event>> = HashMap::new();
+
+        let pool_events = parse_abi(POOL_EVENTS).expect("Invalid pool event definitions");
+        let weth = Contract::load(WETH_ABI.as_bytes()).expect("Unable to parse WETH ABI");
+        let dodo = parsed_abi(DODO_ROUTE_PROXY).expect("DODO ABI is not loaded");
+
+        let events = pool_events
+            .events()
+            .chain(weth.events().filter(|e| e.name == "Deposit" || e.name == "Withdrawal"))
+            .chain(dodo.events().filter(|e| e.name == "OrderHistory"));
+
+        for event in events {
+            m.entry(event.signature()).or_default().push(event.clone());
+        }
+        m
+    };
+}
+
+/// Decodes every log of `receipt` we recognise, in log order. Unknown logs
+/// (and ERC721 `Transfer`s, which share the ERC20 topic) are skipped.
+pub fn decode_receipt(receipt: &TransactionReceipt) -> Vec<DecodedLog> {
+    receipt.logs.iter().filter_map(decode_log).collect()
+}
+
+pub fn decode_log(log: &Log) -> Option<DecodedLog> {
+    let topic0 = log.topics.first()?;
+    let candidates = EVENT_REGISTRY.get(topic0)?;
+
+    // Events sharing a topic0 differ in how many params are indexed, so the
+    // first one that parses is the right one.
+    let parsed = candidates.iter().find_map(|event| {
+        event
+            .parse_log(RawLog {
+                topics: log.topics.clone(),
+                data: log.data.to_vec(),
+            })
+            .ok()
+            .map(|parsed| (event, parsed))
+    })?;
+    let (event, parsed) = parsed;
+
+    let params: HashMap<String, Token> = parsed
+        .params
+        .into_iter()
+        .map(|param| (param.name, param.value))
+        .collect();
+    let address = |name: &str| params.get(name).and_then(|t| t.clone().into_address());
+    let uint = |name: &str| params.get(name).and_then(|t| t.clone().into_uint());
+    let int = |name: &str| {
+        params
+            .get(name)
+            .and_then(|t| t.clone().into_int())
+            .map(I256::from_raw)
+    };
+
+    let decoded = match (event.name.as_str(), event.inputs.len()) {
+        ("Swap", 6) => DecodedEvent::UniswapV2Swap {
+            sender: address("sender")?,
+            amount0_in: uint("amount0In")?,
+            amount1_in: uint("amount1In")?,
+            amount0_out: uint("amount0Out")?,
+            amount1_out: uint("amount1Out")?,
+            to: address("to")?,
+        },
+        ("Sync", _) => DecodedEvent::UniswapV2Sync {
+            reserve0: uint("reserve0")?,
+            reserve1: uint("reserve1")?,
+        },
+        ("Swap", 7) => DecodedEvent::UniswapV3Swap {
+            sender: address("sender")?,
+            recipient: address("recipient")?,
+            amount0: int("amount0")?,
+            amount1: int("amount1")?,
+            sqrt_price_x96: uint("sqrtPriceX96")?,
+            liquidity: uint("liquidity")?,
+            tick: int("tick")?.as_i32(),
+        },
+        ("Transfer", _) => DecodedEvent::Transfer {
+            from: address("from")?,
+            to: address("to")?,
+            value: uint("value")?,
+        },
+        ("Approval", _) => DecodedEvent::Approval {
+            owner: address("owner")?,
+            spender: address("spender")?,
+            value: uint("value")?,
+        },
+        ("Deposit", _) => DecodedEvent::Deposit {
+            dst: address("dst")?,
+            wad: uint("wad")?,
+        },
+        ("Withdrawal", _) => DecodedEvent::Withdrawal {
+            src: address("src")?,
+            wad: uint("wad")?,
+        },
+        ("OrderHistory", _) => DecodedEvent::DodoOrderHistory {
+            from_token: address("fromToken")?,
+            to_token: address("toToken")?,
+            sender: address("sender")?,
+            from_amount: uint("fromAmount")?,
+            return_amount: uint("returnAmount")?,
+        },
+        ("DODOSwap", _) => DecodedEvent::DodoSwap {
+            from_token: address("fromToken")?,
+            to_token: address("toToken")?,
+            from_amount: uint("fromAmount")?,
+            to_amount: uint("toAmount")?,
+            trader: address("trader")?,
+            receiver: address("receiver")?,
+        },
+        _ => return None,
+    };
+
+    Some(DecodedLog {
+        address: log.address,
+        log_index: log.log_index,
+        event: decoded,
+    })
+}
+
+/// Net token movement of `owner` across `logs`, from ERC20 `Transfer`s and
+/// WETH `Deposit`/`Withdrawal`. Handy for checking what a swap actually paid
+/// and received.
+pub fn net_token_flows(logs: &[DecodedLog], owner: Address) -> HashMap<Address, I256> {
+    let mut flows: HashMap<Address, I256> = HashMap::new();
+    let mut add = |token: Address, amount: U256, incoming: bool| {
+        let amount = I256::from_raw(amount);
+        let entry = flows.entry(token).or_insert_with(I256::zero);
+        *entry = if incoming {
+            *entry + amount
+        } else {
+            *entry - amount
+        };
+    };
+
+    for log in logs {
+        match &log.event {
+            DecodedEvent::Transfer { from, to, value } => {
+                if *to == owner {
+                    add(log.address, *value, true);
+                }
+                if *from == owner {
+                    add(log.address, *value, false);
+                }
+            }
+            DecodedEvent::Deposit { dst, wad } if *dst == owner => add(log.address, *wad, true),
+            DecodedEvent::Withdrawal { src, wad } if *src == owner => add(log.address, *wad, false),
+            _ => {}
+        }
+    }
+
+    flows
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::{encode, Token},
+        types::{Bytes, TransactionReceipt},
+        utils::keccak256,
+    };
+
+    use super::*;
+
+    fn topic(signature: &str) -> H256 {
+        H256::from(keccak256(signature))
+    }
+
+    fn indexed(address: Address) -> H256 {
+        H256::from(address)
+    }
+
+    fn log(address: Address, topics: Vec<H256>, data: Vec<Token>) -> Log {
+        Log {
+            address,
+            topics,
+            data: Bytes::from(encode(&data)),
+            ..Default::default()
+        }
+    }
+
+    fn receipt(logs: Vec<Log>) -> TransactionReceipt {
+        TransactionReceipt {
+            logs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn skips_erc721_transfers_sharing_the_erc20_topic() {
+        let token = Address::repeat_byte(0xaa);
+        let nft = Address::repeat_byte(0xbb);
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let transfer = topic("Transfer(address,address,uint256)");
+
+        let logs = decode_receipt(&receipt(vec![
+            log(
+                token,
+                vec![transfer, indexed(alice), indexed(bob)],
+                vec![Token::Uint(U256::from(5))],
+            ),
+            log(
+                nft,
+                vec![
+                    transfer,
+                    indexed(alice),
+                    indexed(bob),
+                    H256::from_low_u64_be(7),
+                ],
+                vec![],
+            ),
+        ]));
+
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].address, token);
+        assert_eq!(
+            logs[0].event,
+            DecodedEvent::Transfer {
+                from: alice,
+                to: bob,
+                value: U256::from(5),
+            }
+        );
+    }
+
+    #[test]
+    fn nets_transfers_and_weth_deposits_and_withdrawals() {
+        let weth = Address::repeat_byte(0xee);
+        let token = Address::repeat_byte(0xaa);
+        let (owner, pool) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let transfer = topic("Transfer(address,address,uint256)");
+        let amount = |n: u64| vec![Token::Uint(U256::from(n))];
+
+        let logs = decode_receipt(&receipt(vec![
+            log(
+                weth,
+                vec![topic("Deposit(address,uint256)"), indexed(owner)],
+                amount(100),
+            ),
+            log(
+                weth,
+                vec![transfer, indexed(owner), indexed(pool)],
+                amount(100),
+            ),
+            log(
+                token,
+                vec![transfer, indexed(pool), indexed(owner)],
+                amount(40),
+            ),
+            log(
+                weth,
+                vec![transfer, indexed(pool), indexed(owner)],
+                amount(30),
+            ),
+            log(
+                weth,
+                vec![topic("Withdrawal(address,uint256)"), indexed(owner)],
+                amount(30),
+            ),
+        ]));
+        assert_eq!(logs.len(), 5);
+
+        let flows = net_token_flows(&logs, owner);
+
+        assert_eq!(flows[&weth], I256::zero());
+        assert_eq!(flows[&token], I256::from(40));
+    }
+}
@@ -1,4 +1,22 @@
 pub mod listener;
+pub mod logs;
 // pub mod processor;
+pub mod builtin_decoders;
+pub mod capture;
+pub mod chains;
+pub mod connection;
+pub mod contracts;
 pub mod decoder;
+pub mod feed;
+pub mod filter;
+pub mod intent;
+pub mod lifecycle;
+pub mod path;
+pub mod pool;
+pub mod protocol;
+pub mod replay;
+pub mod revert;
+pub mod seen;
+pub mod tokens;
+pub mod universal_router;
 pub mod utils;
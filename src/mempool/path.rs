@@ -0,0 +1,145 @@
+use std::error::Error;
+
+use ethabi::Address;
+
+use crate::types::decoded::{DecodedCall, Hop};
+
+const ADDR_SIZE: usize = 20;
+const FEE_SIZE: usize = 3;
+const NEXT_OFFSET: usize = ADDR_SIZE + FEE_SIZE;
+
+/// Parses a packed Uniswap V3 path (`token | fee | token | fee | token ...`)
+/// into hops in execution order. exactOutput paths are encoded output-first,
+/// so `reversed` flips them back into the order the swaps actually happen.
+pub fn decode_v3_path(path: &[u8], reversed: bool) -> Result<Vec<Hop>, Box<dyn Error>> {
+    if path.len() < ADDR_SIZE + NEXT_OFFSET || !(path.len() - ADDR_SIZE).is_multiple_of(NEXT_OFFSET)
+    {
+        return Err(format!("Invalid V3 path length: {} bytes", path.len()).into());
+    }
+
+    let mut hops: Vec<Hop> = path
+        .windows(ADDR_SIZE + NEXT_OFFSET)
+        .step_by(NEXT_OFFSET)
+        .map(|pool| {
+            let first = Address::from_slice(&pool[..ADDR_SIZE]);
+            let fee =
+                u32::from_be_bytes([0, pool[ADDR_SIZE], pool[ADDR_SIZE + 1], pool[ADDR_SIZE + 2]]);
+            let second = Address::from_slice(&pool[NEXT_OFFSET..]);
+
+            if reversed {
+                Hop {
+                    token_in: second,
+                    fee: Some(fee),
+                    token_out: first,
+                }
+            } else {
+                Hop {
+                    token_in: first,
+                    fee: Some(fee),
+                    token_out: second,
+                }
+            }
+        })
+        .collect();
+
+    if reversed {
+        hops.reverse();
+    }
+
+    Ok(hops)
+}
+
+/// Turns a V2 `address[]` path into consecutive pair hops.
+pub fn v2_path_hops(path: &[Address]) -> Vec<Hop> {
+    path.windows(2)
+        .map(|pair| Hop {
+            token_in: pair[0],
+            fee: None,
+            token_out: pair[1],
+        })
+        .collect()
+}
+
+/// Fills `route` on every node of a router call tree that carries a path:
+/// the packed path inside exactInput/exactOutput params, or a V2 `path`
+/// address array. Nodes that already have a route are left alone.
+pub fn fill_routes(call: &mut DecodedCall) {
+    if call.route.is_empty() {
+        call.route = match call.function.as_str() {
+            "exactInput" | "exactOutput" => call
+                .params
+                .first()
+                .and_then(|param| param.value.clone().into_tuple())
+                .and_then(|fields| fields.into_iter().next())
+                .and_then(|path| path.into_bytes())
+                .and_then(|path| decode_v3_path(&path, call.function == "exactOutput").ok())
+                .unwrap_or_default(),
+            _ => call
+                .param("path")
+                .and_then(|path| path.clone().into_array())
+                .map(|path| {
+                    let path: Vec<_> = path.into_iter().filter_map(|t| t.into_address()).collect();
+                    v2_path_hops(&path)
+                })
+                .unwrap_or_default(),
+        };
+    }
+
+    for child in &mut call.children {
+        fill_routes(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn packed(tokens: &[Address], fees: &[u32]) -> Vec<u8> {
+        let mut path = tokens[0].as_bytes().to_vec();
+        for (token, fee) in tokens[1..].iter().zip(fees) {
+            path.extend_from_slice(&fee.to_be_bytes()[1..]);
+            path.extend_from_slice(token.as_bytes());
+        }
+        path
+    }
+
+    fn hop(token_in: Address, fee: u32, token_out: Address) -> Hop {
+        Hop {
+            token_in,
+            fee: Some(fee),
+            token_out,
+        }
+    }
+
+    #[test]
+    fn decodes_a_single_hop() {
+        let hops = decode_v3_path(&packed(&[token(1), token(2)], &[500]), false).unwrap();
+
+        assert_eq!(hops, vec![hop(token(1), 500, token(2))]);
+    }
+
+    #[test]
+    fn reverses_exact_output_paths_into_execution_order() {
+        // exactOutput encodes C <- B <- A, output token first.
+        let path = packed(&[token(3), token(2), token(1)], &[3000, 500]);
+        let hops = decode_v3_path(&path, true).unwrap();
+
+        assert_eq!(
+            hops,
+            vec![hop(token(1), 500, token(2)), hop(token(2), 3000, token(3))]
+        );
+    }
+
+    #[test]
+    fn rejects_paths_of_the_wrong_length() {
+        let mut path = packed(&[token(1), token(2)], &[500]);
+        path.push(0);
+
+        assert!(decode_v3_path(&path, false).is_err());
+        assert!(decode_v3_path(token(1).as_bytes(), false).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use ethers::types::U256;
+use tokio::sync::Notify;
+
+use crate::types::settings::DropPolicy;
+
+/// Counters of a `WorkQueue`, readable while it runs.
+#[derive(Debug, Default)]
+pub struct QueueStats {
+    enqueued: AtomicU64,
+    dropped: AtomicU64,
+    completed: AtomicU64,
+}
+
+impl QueueStats {
+    pub fn enqueued(&self) -> u64 {
+        self.enqueued.load(Ordering::Relaxed)
+    }
+
+    /// Items discarded because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn completed(&self) -> u64 {
+        self.completed.load(Ordering::Relaxed)
+    }
+
+    /// Items waiting or being worked on.
+    pub fn backlog(&self) -> u64 {
+        self.enqueued()
+            .saturating_sub(self.dropped())
+            .saturating_sub(self.completed())
+    }
+}
+
+/// Bounded FIFO in front of the worker pool. When full, `policy` decides
+/// which item gives way.
+pub struct WorkQueue<T> {
+    items: Mutex<Items<T>>,
+    capacity: usize,
+    policy: DropPolicy,
+    notify: Notify,
+    stats: Arc<QueueStats>,
+}
+
+impl<T: Send + 'static> WorkQueue<T> {
+    pub fn new(capacity: usize, policy: DropPolicy, stats: Arc<QueueStats>) -> Self {
+        WorkQueue {
+            items: Mutex::new(Items::default()),
+            capacity: capacity.max(1),
+            policy,
+            notify: Notify::new(),
+            stats,
+        }
+    }
+
+    pub fn stats(&self) -> &QueueStats {
+        &self.stats
+    }
+
+    /// Queues `item`. `priority` is only used by `DropPolicy::LowestGasPrice`;
+    /// items without one (hashes not fetched yet) give way oldest first, before
+    /// any item that has one. Returns false if `item` itself was dropped.
+    pub fn push(&self, priority: Option<U256>, item: T) -> bool {
+        self.stats.enqueued.fetch_add(1, Ordering::Relaxed);
+
+        let mut items = self.items.lock().unwrap();
+        if items.len() >= self.capacity {
+            self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+
+            let evict = match self.policy {
+                DropPolicy::Oldest => items.oldest(),
+                DropPolicy::Newest => None,
+                DropPolicy::LowestGasPrice => match (items.oldest_unpriced(), priority) {
+                    (Some(oldest), _) => Some(oldest),
+                    (None, Some(priority)) => items.lowest_priced(priority),
+                    (None, None) => None,
+                },
+            };
+
+            match evict {
+                Some(seq) => items.remove(seq),
+                None => return false,
+            };
+        }
+
+        items.insert(priority, item);
+        drop(items);
+        self.notify.notify_one();
+        true
+    }
+
+    async fn pop(&self) -> T {
+        loop {
+            if let Some(item) = self.try_pop() {
+                return item;
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    fn try_pop(&self) -> Option<T> {
+        let mut items = self.items.lock().unwrap();
+        let oldest = items.oldest()?;
+        items.remove(oldest)
+    }
+}
+
+/// Queued items in arrival order, indexed by priority so every policy
+/// finds its victim without scanning the queue.
+struct Items<T> {
+    next: u64,
+    by_arrival: BTreeMap<u64, (Option<U256>, T)>,
+    priced: BTreeSet<(U256, u64)>,
+    unpriced: BTreeSet<u64>,
+}
+
+impl<T> Default for Items<T> {
+    fn default() -> Self {
+        Items {
+            next: 0,
+            by_arrival: BTreeMap::new(),
+            priced: BTreeSet::new(),
+            unpriced: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T> Items<T> {
+    fn len(&self) -> usize {
+        self.by_arrival.len()
+    }
+
+    fn insert(&mut self, priority: Option<U256>, item: T) {
+        let seq = self.next;
+        self.next += 1;
+
+        match priority {
+            Some(priority) => self.priced.insert((priority, seq)),
+            None => self.unpriced.insert(seq),
+        };
+        self.by_arrival.insert(seq, (priority, item));
+    }
+
+    fn remove(&mut self, seq: u64) -> Option<T> {
+        let (priority, item) = self.by_arrival.remove(&seq)?;
+        match priority {
+            Some(priority) => self.priced.remove(&(priority, seq)),
+            None => self.unpriced.remove(&seq),
+        };
+        Some(item)
+    }
+
+    fn oldest(&self) -> Option<u64> {
+        self.by_arrival.keys().next().copied()
+    }
+
+    fn oldest_unpriced(&self) -> Option<u64> {
+        self.unpriced.first().copied()
+    }
+
+    /// The lowest-priced item, if it's cheaper than `priority`.
+    fn lowest_priced(&self, priority: U256) -> Option<u64> {
+        self.priced
+            .first()
+            .filter(|(lowest, _)| *lowest < priority)
+            .map(|(_, seq)| *seq)
+    }
+}
+
+/// Starts `concurrency` workers that take items off `queue` and run `work` on
+/// each, one at a time per worker.
+pub fn spawn_workers<T, F, Fut>(queue: Arc<WorkQueue<T>>, concurrency: usize, work: F)
+where
+    T: Send + 'static,
+    F: Fn(T) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    for _ in 0..concurrency.max(1) {
+        let queue = Arc::clone(&queue);
+        let work = work.clone();
+
+        tokio::task::spawn(async move {
+            loop {
+                let item = queue.pop().await;
+                work(item).await;
+                queue.stats.completed.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(policy: DropPolicy) -> WorkQueue<u32> {
+        WorkQueue::new(2, policy, Arc::new(QueueStats::default()))
+    }
+
+    fn drain(queue: &WorkQueue<u32>) -> Vec<u32> {
+        std::iter::from_fn(|| queue.try_pop()).collect()
+    }
+
+    #[test]
+    fn drops_the_lowest_gas_price_first() {
+        let queue = queue(DropPolicy::LowestGasPrice);
+        queue.push(Some(U256::from(5)), 1);
+        queue.push(Some(U256::from(1)), 2);
+
+        assert!(queue.push(Some(U256::from(3)), 3));
+        assert!(!queue.push(Some(U256::from(2)), 4));
+
+        assert_eq!(drain(&queue), vec![1, 3]);
+        assert_eq!(queue.stats().dropped(), 2);
+    }
+
+    #[test]
+    fn drops_unpriced_items_oldest_first() {
+        let queue = queue(DropPolicy::LowestGasPrice);
+        queue.push(None, 1);
+        queue.push(None, 2);
+
+        assert!(queue.push(None, 3));
+        assert!(queue.push(Some(U256::zero()), 4));
+
+        assert_eq!(drain(&queue), vec![3, 4]);
+    }
+}
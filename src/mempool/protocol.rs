@@ -0,0 +1,188 @@
+use std::error::Error;
+
+use ethers::types::Address;
+
+use crate::types::decoded::DecodedTransaction;
+
+use super::{
+    builtin_decoders::{
+        DodoDecoder, UniswapV2Decoder, UniswapV3Decoder, UniversalRouterDecoder, UserAbiDecoder,
+    },
+    contracts::{ContractRegistry, KnownContract},
+};
+
+/// What a `ProtocolDecoder` gets to look at for one transaction.
+pub struct TxContext<'a> {
+    pub to: Address,
+    pub input: &'a [u8],
+    /// The registry entry for `to`. `None` either because `to` is unknown or
+    /// because this is the selector-only fallback pass.
+    pub contract: Option<&'a KnownContract>,
+}
+
+/// Decodes calldata for one protocol. Implement this in your own crate and
+/// add it with `DecoderRegistry::register` to decode routers this crate
+/// doesn't ship with.
+pub trait ProtocolDecoder: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Cheap check whether `decode` should be attempted for `tx`.
+    fn can_decode(&self, tx: &TxContext) -> bool;
+
+    fn decode(&self, tx: &TxContext) -> Result<DecodedTransaction, Box<dyn Error>>;
+}
+
+/// The decoders the listener runs every transaction through, in priority
+/// order, plus the known-contract registry used to scope them.
+pub struct DecoderRegistry {
+    contracts: ContractRegistry,
+    decoders: Vec<Box<dyn ProtocolDecoder>>,
+}
+
+impl DecoderRegistry {
+    pub fn new(contracts: ContractRegistry) -> Self {
+        DecoderRegistry {
+            contracts,
+            decoders: Vec::new(),
+        }
+    }
+
+    pub fn with_builtin(contracts: ContractRegistry) -> Self {
+        let mut registry = DecoderRegistry::new(contracts);
+        registry.register_builtin();
+        registry
+    }
+
+    /// Adds `decoder` after the ones already registered. Register custom
+    /// decoders before `register_builtin` to give them priority.
+    pub fn register(&mut self, decoder: Box<dyn ProtocolDecoder>) {
+        self.decoders.push(decoder);
+    }
+
+    pub fn register_builtin(&mut self) {
+        self.register(Box::new(UniversalRouterDecoder));
+        self.register(Box::new(UniswapV3Decoder));
+        self.register(Box::new(UniswapV2Decoder));
+        self.register(Box::new(DodoDecoder));
+        self.register(Box::new(UserAbiDecoder));
+    }
+
+    pub fn contracts(&self) -> &ContractRegistry {
+        &self.contracts
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.decoders.iter().map(|decoder| decoder.name()).collect()
+    }
+
+    /// Tries every decoder with `to`'s registry entry first, then again as a
+    /// selector-only fallback. The first decoder that accepts and succeeds
+    /// wins; a decoder that fails hands over to the next one, and its error is
+    /// only returned if none succeeds.
+    pub fn decode(
+        &self,
+        to: Address,
+        input: &[u8],
+    ) -> Result<Option<DecodedTransaction>, Box<dyn Error>> {
+        let known = self.contracts.get(&to);
+
+        let passes = [known, None];
+        let passes = if known.is_some() {
+            &passes[..]
+        } else {
+            &passes[1..]
+        };
+
+        let mut first_error = None;
+        for contract in passes {
+            let tx = TxContext {
+                to,
+                input,
+                contract: *contract,
+            };
+
+            for decoder in self.decoders.iter().filter(|d| d.can_decode(&tx)) {
+                match decoder.decode(&tx) {
+                    Ok(decoded) => return Ok(Some(decoded)),
+                    Err(e) => {
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::{encode, Token},
+        types::U256,
+        utils::keccak256,
+    };
+
+    use crate::types::decoded::Protocol;
+
+    use super::*;
+
+    /// Claims every transaction and fails on all of them.
+    struct FailingDecoder;
+
+    impl ProtocolDecoder for FailingDecoder {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn can_decode(&self, _tx: &TxContext) -> bool {
+            true
+        }
+
+        fn decode(&self, _tx: &TxContext) -> Result<DecodedTransaction, Box<dyn Error>> {
+            Err("unsupported".into())
+        }
+    }
+
+    fn v2_swap() -> Vec<u8> {
+        let mut input =
+            keccak256("swapExactTokensForTokens(uint256,uint256,address[],address,uint256)")[..4]
+                .to_vec();
+        input.extend(encode(&[
+            Token::Uint(U256::exp10(18)),
+            Token::Uint(U256::zero()),
+            Token::Array(vec![
+                Token::Address(Address::repeat_byte(1)),
+                Token::Address(Address::repeat_byte(2)),
+            ]),
+            Token::Address(Address::repeat_byte(3)),
+            Token::Uint(U256::from(1_900_000_000u64)),
+        ]));
+        input
+    }
+
+    #[test]
+    fn falls_through_to_the_next_decoder_on_error() {
+        let mut registry = DecoderRegistry::new(ContractRegistry::default());
+        registry.register(Box::new(FailingDecoder));
+        registry.register_builtin();
+
+        let decoded = registry
+            .decode(Address::zero(), &v2_swap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(decoded.protocol, Protocol::UniswapV2);
+    }
+
+    #[test]
+    fn returns_the_error_when_every_decoder_fails() {
+        let mut registry = DecoderRegistry::new(ContractRegistry::default());
+        registry.register(Box::new(FailingDecoder));
+
+        assert!(registry.decode(Address::zero(), &v2_swap()).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufRead, BufReader, Lines},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use flate2::read::MultiGzDecoder;
+use tokio::time::Instant;
+
+use crate::types::{recorded::RecordedTransaction, settings::Pacing};
+
+/// `path` itself, or the recorded transaction files in it (`.jsonl`,
+/// `.ndjson`, optionally `.gz`) sorted by name.
+pub fn recorded_files(path: impl AsRef<Path>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let file = entry?.path();
+        let name = file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        let name = name.strip_suffix(".gz").unwrap_or(name);
+        if name.ends_with(".jsonl") || name.ends_with(".ndjson") {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Reads a JSONL file of `RecordedTransaction`s, one per line, gunzipping
+/// it if it ends in `.gz`. Blank lines are skipped; errors name the line.
+/// Reading stops after the first I/O error, such as a truncated gzip file.
+pub struct RecordedReader {
+    lines: Lines<Box<dyn BufRead>>,
+    line: usize,
+    failed: bool,
+}
+
+impl RecordedReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).map_err(|e| format!("Unable to open {}: {}", path.display(), e))?;
+
+        let reader: Box<dyn BufRead> = if path.extension().is_some_and(|ext| ext == "gz") {
+            Box::new(BufReader::new(MultiGzDecoder::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+
+        Ok(RecordedReader {
+            lines: reader.lines(),
+            line: 0,
+            failed: false,
+        })
+    }
+
+    /// Number of the line last read.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Iterator for RecordedReader {
+    type Item = Result<RecordedTransaction, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            let line = self.lines.next()?;
+            self.line += 1;
+
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(format!("line {}: {}", self.line, e)));
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            return Some(
+                serde_json::from_str(&line).map_err(|e| format!("line {}: {}", self.line, e)),
+            );
+        }
+    }
+}
+
+/// Spaces out replayed transactions according to `Pacing`.
+pub struct Pacer {
+    pacing: Pacing,
+    /// When the first transaction was replayed and first seen.
+    start: Option<(Instant, DateTime<Utc>)>,
+}
+
+impl Pacer {
+    pub fn new(pacing: Pacing) -> Self {
+        Pacer {
+            pacing,
+            start: None,
+        }
+    }
+
+    /// Waits until a transaction first seen at `first_seen` is due. In real
+    /// time, that's as long after the first one as it was recorded; records
+    /// out of order are replayed right away.
+    pub async fn wait(&mut self, first_seen: DateTime<Utc>) {
+        if self.pacing == Pacing::Asap {
+            return;
+        }
+
+        let (started, first) = *self.start.get_or_insert((Instant::now(), first_seen));
+        if let Ok(offset) = (first_seen - first).to_std() {
+            tokio::time::sleep_until(started + offset).await;
+        }
+    }
+}
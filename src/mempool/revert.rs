@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use ethabi::{AbiError, ParamType, Token};
+use ethers::types::U256;
+
+use crate::types::decoded::{DecodedParam, RevertReason};
+
+use super::utils::{keccak256, PARSED_ABIS};
+
+/// How many nested `ExecutionFailed` wrappers are unwrapped before the rest is
+/// reported as unknown data.
+const MAX_REVERT_DEPTH: usize = 8;
+
+lazy_static::lazy_static! {
+    static ref ERROR_STRING_SELECTOR: [u8; 4] = keccak256("Error(string)");
+    static ref PANIC_SELECTOR: [u8; 4] = keccak256("Panic(uint256)");
+
+    /// Custom errors of every loaded ABI, keyed by selector. The first ABI
+    /// listed in `loaded_abis` wins on duplicates.
+    pub static ref ERROR_REGISTRY: HashMap<[u8; 4], AbiError> = {
+        let mut m = HashMap::new();
+        for abi in PARSED_ABIS.iter() {
+            for error in abi.contract.errors() {
+                let mut selector = [0u8; 4];
+                selector.copy_from_slice(&error.signature()[..4]);
+                m.entry(selector).or_insert_with(|| error.clone());
+            }
+        }
+        m
+    };
+}
+
+/// Decodes the return data of a reverted call into a structured reason.
+pub fn decode_revert(data: &[u8]) -> RevertReason {
+    decode_revert_at(data, 0)
+}
+
+fn decode_revert_at(data: &[u8], depth: usize) -> RevertReason {
+    if data.is_empty() {
+        return RevertReason::Empty;
+    }
+    if data.len() < 4 || depth > MAX_REVERT_DEPTH {
+        return RevertReason::Unknown(data.to_vec());
+    }
+
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&data[..4]);
+    let body = &data[4..];
+
+    if selector == *ERROR_STRING_SELECTOR {
+        if let Ok(mut tokens) = ethabi::decode(&[ParamType::String], body) {
+            if let Some(Token::String(message)) = tokens.pop() {
+                return RevertReason::Error(message);
+            }
+        }
+    } else if selector == *PANIC_SELECTOR {
+        if let Ok(mut tokens) = ethabi::decode(&[ParamType::Uint(256)], body) {
+            if let Some(Token::Uint(code)) = tokens.pop() {
+                return RevertReason::Panic {
+                    code,
+                    description: panic_description(code).to_string(),
+                };
+            }
+        }
+    } else if let Some(error) = ERROR_REGISTRY.get(&selector) {
+        if let Ok(tokens) = error.decode(body) {
+            if error.name == "ExecutionFailed" {
+                if let [Token::Uint(command_index), Token::Bytes(inner)] = tokens.as_slice() {
+                    return RevertReason::ExecutionFailed {
+                        command_index: *command_index,
+                        reason: Box::new(decode_revert_at(inner, depth + 1)),
+                    };
+                }
+            }
+
+            let signature = format!(
+                "{}({})",
+                error.name,
+                error
+                    .inputs
+                    .iter()
+                    .map(|param| param.kind.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            );
+
+            return RevertReason::Custom {
+                name: error.name.clone(),
+                signature,
+                params: error
+                    .inputs
+                    .iter()
+                    .zip(tokens)
+                    .map(|(param, value)| DecodedParam {
+                        name: param.name.clone(),
+                        kind: param.kind.clone(),
+                        value,
+                    })
+                    .collect(),
+            };
+        }
+    }
+
+    RevertReason::Unknown(data.to_vec())
+}
+
+/// Solidity panic codes, see the "Panic via assert and Error via require"
+/// section of the Solidity docs.
+fn panic_description(code: U256) -> &'static str {
+    if code > U256::from(u8::MAX) {
+        return "unknown panic code";
+    }
+
+    match code.as_u32() {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "incorrectly encoded storage byte array",
+        0x31 => "pop on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to zero-initialized function",
+        _ => "unknown panic code",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethabi::encode;
+
+    use super::*;
+
+    fn revert_data(signature: &str, params: &[Token]) -> Vec<u8> {
+        let mut data = keccak256(signature).to_vec();
+        data.extend(encode(params));
+        data
+    }
+
+    fn execution_failed(command_index: u64, inner: Vec<u8>) -> Vec<u8> {
+        revert_data(
+            "ExecutionFailed(uint256,bytes)",
+            &[Token::Uint(command_index.into()), Token::Bytes(inner)],
+        )
+    }
+
+    #[test]
+    fn decodes_error_strings() {
+        let data = revert_data(
+            "Error(string)",
+            &[Token::String("Too little received".into())],
+        );
+
+        assert_eq!(
+            decode_revert(&data),
+            RevertReason::Error("Too little received".to_string())
+        );
+    }
+
+    #[test]
+    fn decodes_panics() {
+        let data = revert_data("Panic(uint256)", &[Token::Uint(0x11.into())]);
+
+        assert_eq!(
+            decode_revert(&data),
+            RevertReason::Panic {
+                code: 0x11.into(),
+                description: "arithmetic overflow or underflow".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_custom_errors_from_loaded_abis() {
+        let data = revert_data("InvalidCommandType(uint256)", &[Token::Uint(0x3f.into())]);
+
+        match decode_revert(&data) {
+            RevertReason::Custom {
+                name,
+                signature,
+                params,
+            } => {
+                assert_eq!(name, "InvalidCommandType");
+                assert_eq!(signature, "InvalidCommandType(uint256)");
+                assert_eq!(params[0].value, Token::Uint(0x3f.into()));
+            }
+            other => panic!("expected a custom error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unwraps_nested_execution_failures_up_to_the_depth_limit() {
+        let inner = revert_data("Error(string)", &[Token::String("deep".into())]);
+        let nested = |levels: usize| {
+            (0..levels).fold(inner.clone(), |data, level| {
+                execution_failed(level as u64, data)
+            })
+        };
+        let innermost = |mut reason: RevertReason| loop {
+            match reason {
+                RevertReason::ExecutionFailed { reason: inner, .. } => reason = *inner,
+                reason => return reason,
+            }
+        };
+
+        assert_eq!(
+            innermost(decode_revert(&nested(MAX_REVERT_DEPTH))),
+            RevertReason::Error("deep".to_string())
+        );
+        assert!(matches!(
+            innermost(decode_revert(&nested(MAX_REVERT_DEPTH + 1))),
+            RevertReason::Unknown(_)
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+/// Insertion-ordered map that forgets entries older than `ttl` and, past
+/// `capacity`, the oldest ones first. Re-inserting a key refreshes it.
+#[derive(Debug)]
+pub struct SeenCache<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    /// Keys in insertion order. May hold stale positions of re-inserted keys,
+    /// which are skipped when they reach the front.
+    order: VecDeque<(u64, Instant, K)>,
+    next_sequence: u64,
+    ttl: Duration,
+    capacity: usize,
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    sequence: u64,
+    value: V,
+}
+
+impl<K: Hash + Eq + Clone, V> SeenCache<K, V> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        SeenCache {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            next_sequence: 0,
+            ttl,
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Inserts `value` under `key`, returning the value it replaces.
+    pub fn insert(&mut self, key: K, value: V, now: Instant) -> Option<V> {
+        self.evict(now);
+
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        let previous = self.entries.insert(key.clone(), Entry { sequence, value });
+        self.order.push_back((sequence, now, key));
+
+        while self.entries.len() > self.capacity {
+            self.pop_oldest();
+        }
+
+        previous.map(|entry| entry.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops entries older than `ttl`.
+    pub fn evict(&mut self, now: Instant) {
+        while let Some((_, inserted, _)) = self.order.front() {
+            if now.duration_since(*inserted) < self.ttl {
+                break;
+            }
+            self.pop_oldest();
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        while let Some((sequence, _, key)) = self.order.pop_front() {
+            let current = self
+                .entries
+                .get(&key)
+                .is_some_and(|entry| entry.sequence == sequence);
+            if current {
+                self.entries.remove(&key);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use ethers::{
+    abi::{decode, ParamType},
+    types::{Address, Bytes, TransactionRequest, U256},
+};
+use ethers_providers::{JsonRpcError, Middleware, MiddlewareError};
+use log::{debug, warn};
+
+use crate::types::token::TokenMetadata;
+
+use super::{
+    chains::ChainProfile,
+    utils::{keccak256, NATIVE_ETH},
+};
+
+/// Resolves ERC20 symbol, name and decimals with `eth_call` and keeps the
+/// results in memory and in a JSON file at `path`, so each token is only
+/// looked up once across runs.
+pub struct TokenMetadataService {
+    tokens: Arc<RwLock<HashMap<Address, TokenMetadata>>>,
+    /// Addresses that didn't answer like an ERC20 this run.
+    failed: RwLock<HashSet<Address>>,
+    file: Arc<CacheFile>,
+}
+
+/// The JSON file behind the cache. It's rewritten on the blocking pool, one
+/// write at a time; a write that hasn't started yet also covers tokens
+/// resolved in the meantime.
+struct CacheFile {
+    path: PathBuf,
+    pending: AtomicBool,
+    writing: Mutex<()>,
+}
+
+impl TokenMetadataService {
+    /// Loads the cache at `path`. A missing file starts an empty cache; an
+    /// unreadable one is an error. The native token placeholder is named
+    /// after `chain`'s native currency.
+    pub fn load(path: impl Into<PathBuf>, chain: &ChainProfile) -> Result<Self, Box<dyn Error>> {
+        let path = path.into();
+
+        let mut tokens: HashMap<Address, TokenMetadata> = match fs::read_to_string(&path) {
+            Ok(file) => serde_json::from_str(&file)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        tokens.insert(
+            *NATIVE_ETH,
+            TokenMetadata {
+                symbol: chain.native_symbol.to_string(),
+                name: chain.native_name.to_string(),
+                decimals: 18,
+            },
+        );
+
+        Ok(TokenMetadataService {
+            tokens: Arc::new(RwLock::new(tokens)),
+            failed: RwLock::new(HashSet::new()),
+            file: Arc::new(CacheFile {
+                path,
+                pending: AtomicBool::new(false),
+                writing: Mutex::new(()),
+            }),
+        })
+    }
+
+    pub fn get(&self, token: &Address) -> Option<TokenMetadata> {
+        self.tokens.read().unwrap().get(token).cloned()
+    }
+
+    /// Cached metadata for `token`, fetching it through `provider` on a miss
+    /// if there is one. `None` if the contract doesn't implement `symbol()`
+    /// and `decimals()`, or if the provider couldn't be reached; only the
+    /// former is remembered.
+    pub async fn resolve<M: Middleware>(
+        &self,
+        provider: Option<&M>,
+        token: Address,
+    ) -> Option<TokenMetadata> {
+        if let Some(metadata) = self.get(&token) {
+            return Some(metadata);
+        }
+        if self.failed.read().unwrap().contains(&token) {
+            return None;
+        }
+        let provider = provider?;
+
+        match fetch_metadata(provider, token).await {
+            Ok(Some(metadata)) => {
+                self.tokens.write().unwrap().insert(token, metadata.clone());
+                self.save();
+                Some(metadata)
+            }
+            Ok(None) => {
+                self.failed.write().unwrap().insert(token);
+                None
+            }
+            Err(e) => {
+                debug!("Token metadata lookup for {:?} failed: {}", token, e);
+                None
+            }
+        }
+    }
+
+    /// Schedules a rewrite of the cache file unless one is already waiting.
+    fn save(&self) {
+        if self.file.pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let tokens = self.tokens.clone();
+        let file = self.file.clone();
+        tokio::task::spawn_blocking(move || {
+            let _writing = file.writing.lock().unwrap();
+            // Cleared before the snapshot, so tokens added after it schedule
+            // another write.
+            file.pending.store(false, Ordering::Release);
+
+            let snapshot: HashMap<Address, TokenMetadata> = tokens
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|(address, _)| **address != *NATIVE_ETH)
+                .map(|(address, metadata)| (*address, metadata.clone()))
+                .collect();
+            if let Err(e) = write(&file.path, &snapshot) {
+                warn!("Failed to write token cache {:?}: {}", file.path, e);
+            }
+        });
+    }
+}
+
+fn write(path: &Path, tokens: &HashMap<Address, TokenMetadata>) -> Result<(), Box<dyn Error>> {
+    let json = serde_json::to_string_pretty(&tokens)?;
+
+    // Write then rename so a crash mid-write can't truncate the cache.
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// `Ok(None)` if `token` answered, but not like an ERC20.
+async fn fetch_metadata<M: Middleware>(
+    provider: &M,
+    token: Address,
+) -> Result<Option<TokenMetadata>, M::Error> {
+    let Some(symbol) = call(provider, token, "symbol()")
+        .await?
+        .and_then(|data| decode_text(&data))
+    else {
+        return Ok(None);
+    };
+    let Some(decimals) = call(provider, token, "decimals()")
+        .await?
+        .and_then(|data| decode_decimals(&data))
+    else {
+        return Ok(None);
+    };
+    let name = call(provider, token, "name()")
+        .await?
+        .and_then(|data| decode_text(&data))
+        .unwrap_or_else(|| symbol.clone());
+
+    Ok(Some(TokenMetadata {
+        symbol,
+        name,
+        decimals,
+    }))
+}
+
+/// `Ok(None)` if the call reverted.
+async fn call<M: Middleware>(
+    provider: &M,
+    token: Address,
+    signature: &str,
+) -> Result<Option<Bytes>, M::Error> {
+    let tx = TransactionRequest::new()
+        .to(token)
+        .data(keccak256(signature).to_vec());
+
+    match provider.call(&tx.into(), None).await {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.as_error_response().is_some_and(JsonRpcError::is_revert) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// ABI `string`, or the `bytes32` some early tokens (MKR, SAI) return instead.
+fn decode_text(data: &[u8]) -> Option<String> {
+    let text = if data.len() == 32 {
+        let end = data.iter().position(|b| *b == 0).unwrap_or(32);
+        String::from_utf8(data[..end].to_vec()).ok()?
+    } else {
+        decode(&[ParamType::String], data)
+            .ok()?
+            .pop()?
+            .into_string()?
+    };
+
+    let text = text.trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn decode_decimals(data: &[u8]) -> Option<u8> {
+    if data.len() < 32 {
+        return None;
+    }
+    let decimals = U256::from_big_endian(&data[..32]);
+    (decimals <= U256::from(u8::MAX)).then(|| decimals.as_u32() as u8)
+}
@@ -0,0 +1,452 @@
+use std::error::Error;
+
+use ethabi::ParamType;
+
+use crate::types::decoded::{CallKind, DecodedCall, DecodedParam};
+
+use super::{
+    decoder::MAX_CALL_DEPTH,
+    path::{decode_v3_path, v2_path_hops},
+};
+
+pub const FLAG_ALLOW_REVERT: u8 = 0x80;
+pub const COMMAND_TYPE_MASK: u8 = 0x3f;
+
+pub const V3_SWAP_EXACT_IN: u8 = 0x00;
+pub const V3_SWAP_EXACT_OUT: u8 = 0x01;
+pub const PERMIT2_TRANSFER_FROM: u8 = 0x02;
+pub const PERMIT2_PERMIT_BATCH: u8 = 0x03;
+pub const SWEEP: u8 = 0x04;
+pub const TRANSFER: u8 = 0x05;
+pub const PAY_PORTION: u8 = 0x06;
+pub const V2_SWAP_EXACT_IN: u8 = 0x08;
+pub const V2_SWAP_EXACT_OUT: u8 = 0x09;
+pub const PERMIT2_PERMIT: u8 = 0x0a;
+pub const WRAP_ETH: u8 = 0x0b;
+pub const UNWRAP_WETH: u8 = 0x0c;
+pub const PERMIT2_TRANSFER_FROM_BATCH: u8 = 0x0d;
+pub const BALANCE_CHECK_ERC20: u8 = 0x0e;
+pub const SEAPORT_V1_5: u8 = 0x10;
+pub const LOOKS_RARE_V2: u8 = 0x11;
+pub const NFTX: u8 = 0x12;
+pub const CRYPTOPUNKS: u8 = 0x13;
+pub const OWNER_CHECK_721: u8 = 0x15;
+pub const OWNER_CHECK_1155: u8 = 0x16;
+pub const SWEEP_ERC721: u8 = 0x17;
+pub const X2Y2_721: u8 = 0x18;
+pub const SUDOSWAP: u8 = 0x19;
+pub const NFT20: u8 = 0x1a;
+pub const X2Y2_1155: u8 = 0x1b;
+pub const FOUNDATION: u8 = 0x1c;
+pub const SWEEP_ERC1155: u8 = 0x1d;
+pub const ELEMENT_MARKET: u8 = 0x1e;
+pub const SEAPORT_V1_4: u8 = 0x20;
+pub const EXECUTE_SUB_PLAN: u8 = 0x21;
+pub const APPROVE_ERC20: u8 = 0x22;
+
+/// Decodes the `commands`/`inputs` pair passed to Universal Router `execute`.
+/// Each command byte becomes one child call, decoded against that command's
+/// input layout. A command that fails to decode becomes an error node rather
+/// than failing its siblings. `depth` is the `EXECUTE_SUB_PLAN` nesting level.
+pub fn decode_commands(
+    commands: &[u8],
+    inputs: &[Vec<u8>],
+    depth: usize,
+) -> Result<Vec<DecodedCall>, Box<dyn Error>> {
+    if commands.len() != inputs.len() {
+        return Err(format!(
+            "Universal Router length mismatch: {} commands, {} inputs",
+            commands.len(),
+            inputs.len()
+        )
+        .into());
+    }
+
+    Ok(commands
+        .iter()
+        .zip(inputs)
+        .map(|(command, input)| {
+            decode_command(*command, input, depth)
+                .unwrap_or_else(|e| failed_command(*command, input, e.to_string()))
+        })
+        .collect())
+}
+
+fn command_kind(command: u8) -> CallKind {
+    CallKind::Command {
+        opcode: command & COMMAND_TYPE_MASK,
+        allow_revert: command & FLAG_ALLOW_REVERT != 0,
+    }
+}
+
+fn failed_command(command: u8, input: &[u8], error: String) -> DecodedCall {
+    let name = match command_layout(command & COMMAND_TYPE_MASK) {
+        Some((name, _)) => name.to_string(),
+        None => format!("UNKNOWN_COMMAND_0x{:02x}", command & COMMAND_TYPE_MASK),
+    };
+
+    DecodedCall {
+        signature: format!("{}(bytes)", name),
+        function: name,
+        kind: command_kind(command),
+        params: vec![DecodedParam {
+            name: "input".to_string(),
+            kind: ParamType::Bytes,
+            value: ethabi::Token::Bytes(input.to_vec()),
+        }],
+        route: Vec::new(),
+        children: Vec::new(),
+        error: Some(error),
+    }
+}
+
+fn decode_command(command: u8, input: &[u8], depth: usize) -> Result<DecodedCall, Box<dyn Error>> {
+    let opcode = command & COMMAND_TYPE_MASK;
+    let kind = command_kind(command);
+
+    let (name, layout) = command_layout(opcode).ok_or("Unknown command")?;
+
+    let types: Vec<ParamType> = layout.iter().map(|(_, kind)| kind.clone()).collect();
+    let tokens = ethabi::decode(&types, input)?;
+
+    let params: Vec<DecodedParam> = layout
+        .into_iter()
+        .zip(tokens)
+        .map(|((name, kind), value)| DecodedParam {
+            name: name.to_string(),
+            kind,
+            value,
+        })
+        .collect();
+
+    let route = match opcode {
+        V3_SWAP_EXACT_IN | V3_SWAP_EXACT_OUT => params[3]
+            .value
+            .clone()
+            .into_bytes()
+            .and_then(|path| decode_v3_path(&path, opcode == V3_SWAP_EXACT_OUT).ok())
+            .unwrap_or_default(),
+        V2_SWAP_EXACT_IN | V2_SWAP_EXACT_OUT => {
+            let path: Vec<_> = params[3]
+                .value
+                .clone()
+                .into_array()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|token| token.into_address())
+                .collect();
+            v2_path_hops(&path)
+        }
+        _ => Vec::new(),
+    };
+
+    let children = if opcode == EXECUTE_SUB_PLAN {
+        if depth >= MAX_CALL_DEPTH {
+            return Err(format!("Nesting deeper than {} calls", MAX_CALL_DEPTH).into());
+        }
+
+        let commands = params[0].value.clone().into_bytes().unwrap_or_default();
+        let inputs: Vec<Vec<u8>> = params[1]
+            .value
+            .clone()
+            .into_array()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|input| input.into_bytes())
+            .collect();
+        decode_commands(&commands, &inputs, depth + 1)?
+    } else {
+        Vec::new()
+    };
+
+    let signature = format!(
+        "{}({})",
+        name,
+        types
+            .iter()
+            .map(|kind| kind.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+
+    Ok(DecodedCall {
+        function: name.to_string(),
+        signature,
+        kind,
+        params,
+        route,
+        children,
+        error: None,
+    })
+}
+
+/// Input layouts from the Universal Router `Dispatcher`, keyed by the command
+/// type (flag bits already masked off).
+fn command_layout(opcode: u8) -> Option<(&'static str, Vec<(&'static str, ParamType)>)> {
+    use ParamType::{Address, Array, Bool, Bytes, Tuple, Uint};
+
+    let permit_details = || Tuple(vec![Address, Uint(160), Uint(48), Uint(48)]);
+    let market_call = || vec![("value", Uint(256)), ("data", Bytes)];
+
+    let layout = match opcode {
+        V3_SWAP_EXACT_IN => (
+            "V3_SWAP_EXACT_IN",
+            vec![
+                ("recipient", Address),
+                ("amountIn", Uint(256)),
+                ("amountOutMin", Uint(256)),
+                ("path", Bytes),
+                ("payerIsUser", Bool),
+            ],
+        ),
+        V3_SWAP_EXACT_OUT => (
+            "V3_SWAP_EXACT_OUT",
+            vec![
+                ("recipient", Address),
+                ("amountOut", Uint(256)),
+                ("amountInMax", Uint(256)),
+                ("path", Bytes),
+                ("payerIsUser", Bool),
+            ],
+        ),
+        PERMIT2_TRANSFER_FROM => (
+            "PERMIT2_TRANSFER_FROM",
+            vec![
+                ("token", Address),
+                ("recipient", Address),
+                ("amount", Uint(160)),
+            ],
+        ),
+        PERMIT2_PERMIT_BATCH => (
+            "PERMIT2_PERMIT_BATCH",
+            vec![
+                (
+                    "permitBatch",
+                    Tuple(vec![Array(Box::new(permit_details())), Address, Uint(256)]),
+                ),
+                ("signature", Bytes),
+            ],
+        ),
+        SWEEP => (
+            "SWEEP",
+            vec![
+                ("token", Address),
+                ("recipient", Address),
+                ("amountMin", Uint(256)),
+            ],
+        ),
+        TRANSFER => (
+            "TRANSFER",
+            vec![
+                ("token", Address),
+                ("recipient", Address),
+                ("value", Uint(256)),
+            ],
+        ),
+        PAY_PORTION => (
+            "PAY_PORTION",
+            vec![
+                ("token", Address),
+                ("recipient", Address),
+                ("bips", Uint(256)),
+            ],
+        ),
+        V2_SWAP_EXACT_IN => (
+            "V2_SWAP_EXACT_IN",
+            vec![
+                ("recipient", Address),
+                ("amountIn", Uint(256)),
+                ("amountOutMin", Uint(256)),
+                ("path", Array(Box::new(Address))),
+                ("payerIsUser", Bool),
+            ],
+        ),
+        V2_SWAP_EXACT_OUT => (
+            "V2_SWAP_EXACT_OUT",
+            vec![
+                ("recipient", Address),
+                ("amountOut", Uint(256)),
+                ("amountInMax", Uint(256)),
+                ("path", Array(Box::new(Address))),
+                ("payerIsUser", Bool),
+            ],
+        ),
+        PERMIT2_PERMIT => (
+            "PERMIT2_PERMIT",
+            vec![
+                (
+                    "permitSingle",
+                    Tuple(vec![permit_details(), Address, Uint(256)]),
+                ),
+                ("signature", Bytes),
+            ],
+        ),
+        WRAP_ETH => (
+            "WRAP_ETH",
+            vec![("recipient", Address), ("amountMin", Uint(256))],
+        ),
+        UNWRAP_WETH => (
+            "UNWRAP_WETH",
+            vec![("recipient", Address), ("amountMin", Uint(256))],
+        ),
+        PERMIT2_TRANSFER_FROM_BATCH => (
+            "PERMIT2_TRANSFER_FROM_BATCH",
+            vec![(
+                "batchDetails",
+                Array(Box::new(Tuple(vec![Address, Address, Uint(160), Address]))),
+            )],
+        ),
+        BALANCE_CHECK_ERC20 => (
+            "BALANCE_CHECK_ERC20",
+            vec![
+                ("owner", Address),
+                ("token", Address),
+                ("minBalance", Uint(256)),
+            ],
+        ),
+        SEAPORT_V1_5 => ("SEAPORT_V1_5", market_call()),
+        LOOKS_RARE_V2 => ("LOOKS_RARE_V2", market_call()),
+        NFTX => ("NFTX", market_call()),
+        CRYPTOPUNKS => (
+            "CRYPTOPUNKS",
+            vec![
+                ("punkId", Uint(256)),
+                ("recipient", Address),
+                ("value", Uint(256)),
+            ],
+        ),
+        OWNER_CHECK_721 => (
+            "OWNER_CHECK_721",
+            vec![("owner", Address), ("token", Address), ("id", Uint(256))],
+        ),
+        OWNER_CHECK_1155 => (
+            "OWNER_CHECK_1155",
+            vec![
+                ("owner", Address),
+                ("token", Address),
+                ("id", Uint(256)),
+                ("minBalance", Uint(256)),
+            ],
+        ),
+        SWEEP_ERC721 => (
+            "SWEEP_ERC721",
+            vec![
+                ("token", Address),
+                ("recipient", Address),
+                ("id", Uint(256)),
+            ],
+        ),
+        X2Y2_721 => (
+            "X2Y2_721",
+            vec![
+                ("value", Uint(256)),
+                ("data", Bytes),
+                ("recipient", Address),
+                ("token", Address),
+                ("id", Uint(256)),
+            ],
+        ),
+        SUDOSWAP => ("SUDOSWAP", market_call()),
+        NFT20 => ("NFT20", market_call()),
+        X2Y2_1155 => (
+            "X2Y2_1155",
+            vec![
+                ("value", Uint(256)),
+                ("data", Bytes),
+                ("recipient", Address),
+                ("token", Address),
+                ("id", Uint(256)),
+                ("amount", Uint(256)),
+            ],
+        ),
+        FOUNDATION => (
+            "FOUNDATION",
+            vec![
+                ("value", Uint(256)),
+                ("data", Bytes),
+                ("recipient", Address),
+                ("token", Address),
+                ("id", Uint(256)),
+            ],
+        ),
+        SWEEP_ERC1155 => (
+            "SWEEP_ERC1155",
+            vec![
+                ("token", Address),
+                ("recipient", Address),
+                ("id", Uint(256)),
+                ("amount", Uint(256)),
+            ],
+        ),
+        ELEMENT_MARKET => ("ELEMENT_MARKET", market_call()),
+        SEAPORT_V1_4 => ("SEAPORT_V1_4", market_call()),
+        EXECUTE_SUB_PLAN => (
+            "EXECUTE_SUB_PLAN",
+            vec![("commands", Bytes), ("inputs", Array(Box::new(Bytes)))],
+        ),
+        APPROVE_ERC20 => (
+            "APPROVE_ERC20",
+            vec![("token", Address), ("spender", Uint(256))],
+        ),
+        _ => return None,
+    };
+
+    Some(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::{encode, Token},
+        types::{Address, U256},
+    };
+
+    use super::*;
+
+    fn wrap_input() -> Vec<u8> {
+        encode(&[
+            Token::Address(Address::from_low_u64_be(2)),
+            Token::Uint(U256::exp10(18)),
+        ])
+    }
+
+    #[test]
+    fn masks_the_allow_revert_flag() {
+        let calls = decode_commands(&[FLAG_ALLOW_REVERT | WRAP_ETH], &[wrap_input()], 0).unwrap();
+
+        assert_eq!(calls[0].function, "WRAP_ETH");
+        assert_eq!(
+            calls[0].kind,
+            CallKind::Command {
+                opcode: WRAP_ETH,
+                allow_revert: true
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_commands_as_raw_input() {
+        let calls = decode_commands(&[0x3f], &[vec![1, 2, 3]], 0).unwrap();
+
+        assert_eq!(calls[0].function, "UNKNOWN_COMMAND_0x3f");
+        assert_eq!(calls[0].params[0].value, Token::Bytes(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn rejects_mismatched_commands_and_inputs() {
+        assert!(decode_commands(&[WRAP_ETH, UNWRAP_WETH], &[wrap_input()], 0).is_err());
+    }
+
+    #[test]
+    fn decodes_sub_plans_recursively() {
+        let sub_plan = encode(&[
+            Token::Bytes(vec![WRAP_ETH]),
+            Token::Array(vec![Token::Bytes(wrap_input())]),
+        ]);
+        let calls = decode_commands(&[EXECUTE_SUB_PLAN], &[sub_plan], 0).unwrap();
+
+        assert_eq!(calls[0].function, "EXECUTE_SUB_PLAN");
+        assert_eq!(calls[0].children.len(), 1);
+        assert_eq!(calls[0].children[0].function, "WRAP_ETH");
+    }
+}
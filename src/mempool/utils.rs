@@ -1,46 +1,261 @@
-use std::{collections::HashMap, fs};
+use ethabi::{Contract, Function};
+use ethers::types::Address;
+use log::{info, warn};
+use std::{collections::HashMap, error::Error, fs, path::Path, str::FromStr, sync::OnceLock};
 use tiny_keccak::{Hasher, Keccak};
 
+use crate::types::decoded::Protocol;
+
+use super::decoder::{TARGET_POOL_ABI, UNISWAP_V3_ROUTER_V2, UNIVERSAL_ROUTER_ABI};
+
+pub const UNIVERSAL_ROUTER: &str = "UniswapUniversalRouter";
+pub const UNISWAP_V3_SWAP_ROUTER_02: &str = "UniswapV3SwapRouter02";
+pub const UNISWAP_V3_SWAP_ROUTER: &str = "UniswapV3SwapRouter";
+pub const UNISWAP_V2_ROUTER_02: &str = "UniswapV2Router02";
+pub const DODO_ROUTE_PROXY: &str = "DODORouteProxy";
+
+pub const UNISWAP_V2_ABI: &str = include_str!("../../uniswap/UniswapV2Router.json");
+pub const UNISWAP_V3_ABI: &str = include_str!("../../uniswap/UniswapV3Router.json");
+pub const WETH_ABI: &str = include_str!("../../uniswap/UniswapV3Positions.json");
+
+/// ABIs from the user's `[abis] directory`, set once by `load_user_abis`.
+static USER_ABIS: OnceLock<Vec<ParsedAbi>> = OnceLock::new();
+
 lazy_static::lazy_static! {
-   pub static ref UNIVERSAL_FUNCTION_MAPPING: HashMap<[u8; 4], &'static str> = {
-        let mut m = HashMap::new();
-        m.insert(keccak256("collectRewards(bytes)"), "collectRewards");
-        m.insert(keccak256("execute(bytes,bytes[])"), "execute");
-        m.insert(keccak256("onERC1155BatchReceived(address,address,uint256[],uint256[],bytes)"), "onERC1155BatchReceived");
-        m.insert(keccak256("onERC1155Received(address,address,uint256,uint256,bytes)"), "onERC1155Received");
-
-        // Inserting existing function mappings
-        m.insert([0x7f, 0xf3, 0x6a, 0xb5], "swapExactETHForTokens");
-        m.insert([0xb6, 0xf9, 0xde, 0x95],"swapExactETHForTokensSupportingFeeOnTransferTokens");
-        m.insert([0x38, 0xed, 0x17, 0x39], "swapExactTokensForTokens");
-        m.insert([0x38, 0x75, 0x24, 0x3e], "swapTokensForExactTokens");
-        m.insert([0x18, 0xcb, 0xaf, 0xe5], "swapExactTokensForETH");
-        m.insert([0x4f, 0x36, 0x34, 0xf6], "swapETHForExactTokens");
-        m.insert([0xf3, 0x02, 0xd7, 0x18], "swapTokensForExactETH");
-        m.insert([0x1b, 0xb7, 0xab, 0x1d], "exactInput");
-        m.insert([0x2e, 0x0a, 0x9e, 0x96], "exactInputSingle");
-        m.insert([0x8b, 0x53, 0xb4, 0x36], "exactOutput");
-        m.insert([0xd9, 0xc1, 0xad, 0xed], "exactOutputSingle");
-
-        m.insert([90, 228, 1, 220], "multicall");
-        m.insert([4, 228, 90, 175], "exactInputSingle");
-        m.insert([48, 26, 55, 32], "mixSwap");
-        m.insert([53, 147, 86, 76], "execute");
-
-        m
+    /// Placeholder some routers (DODO, 1inch) use for the native currency.
+    pub static ref NATIVE_ETH: Address = Address::from_str("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE").unwrap();
+
+    /// Every loaded ABI, parsed once and shared by the function, error and
+    /// event registries. User ABIs come after the bundled ones, so they never
+    /// shadow a bundled selector.
+    pub static ref PARSED_ABIS: Vec<ParsedAbi> = {
+        let mut abis = parse_abis(&loaded_abis());
+        abis.extend(USER_ABIS.get_or_init(Vec::new).iter().cloned());
+        abis
     };
 
-    pub static ref TARGET_POOL_MAPPING: HashMap<[u8; 4], &'static str> = {
-        let mut m = HashMap::new();
-        m.insert(keccak256("collectRewards(bytes)"), "collectRewards");
-        m
+    /// Every function of every loaded ABI, keyed by selector. When two ABIs
+    /// share a signature the first one listed in `loaded_abis` wins.
+    pub static ref FUNCTION_REGISTRY: FunctionRegistry = FunctionRegistry::build(&PARSED_ABIS);
+}
+
+/// The bundled ABIs, in lookup priority order.
+pub fn loaded_abis() -> Vec<(&'static str, Protocol, &'static str)> {
+    vec![
+        (
+            UNIVERSAL_ROUTER,
+            Protocol::UniswapUniversalRouter,
+            UNIVERSAL_ROUTER_ABI,
+        ),
+        (
+            UNISWAP_V3_SWAP_ROUTER_02,
+            Protocol::UniswapV3,
+            UNISWAP_V3_ROUTER_V2,
+        ),
+        (UNISWAP_V3_SWAP_ROUTER, Protocol::UniswapV3, UNISWAP_V3_ABI),
+        (UNISWAP_V2_ROUTER_02, Protocol::UniswapV2, UNISWAP_V2_ABI),
+        (DODO_ROUTE_PROXY, Protocol::Dodo, TARGET_POOL_ABI),
+    ]
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedAbi {
+    pub label: &'static str,
+    pub protocol: Protocol,
+    pub contract: Contract,
+}
+
+pub fn parse_abis(abis: &[(&'static str, Protocol, &str)]) -> Vec<ParsedAbi> {
+    abis.iter()
+        .map(|(label, protocol, json)| ParsedAbi {
+            label,
+            protocol: *protocol,
+            contract: Contract::load(json.as_bytes())
+                .unwrap_or_else(|e| panic!("Unable to parse {} ABI: {}", label, e)),
+        })
+        .collect()
+}
+
+/// Parses every `*.json` file in `dir` as an ABI labelled with its file
+/// stem. Accepts a bare ABI array or a build artifact with an `abi` field.
+/// Must run before the first decode; any invalid file fails the whole load.
+pub fn load_user_abis(dir: impl AsRef<Path>) -> Result<usize, Box<dyn Error>> {
+    let dir = dir.as_ref();
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("Unable to read ABI directory {:?}: {}", dir, e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let builtin: Vec<&str> = loaded_abis().iter().map(|(label, _, _)| *label).collect();
+    let mut abis: Vec<ParsedAbi> = Vec::new();
+
+    for path in paths {
+        let label = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("Invalid ABI file name {:?}", path))?;
+        if builtin.contains(&label) || abis.iter().any(|abi| abi.label == label) {
+            return Err(format!("ABI {:?} is already loaded", label).into());
+        }
+
+        let contract =
+            parse_user_abi(&path).map_err(|e| format!("Invalid ABI {:?}: {}", path, e))?;
+        if contract.functions().next().is_none() {
+            return Err(format!("ABI {:?} has no functions", path).into());
+        }
+
+        // Labels live as long as the registries, which is the whole run.
+        let label: &'static str = Box::leak(label.to_string().into_boxed_str());
+        abis.push(ParsedAbi {
+            label,
+            protocol: Protocol::Custom(label),
+            contract,
+        });
+    }
+
+    let count = abis.len();
+    USER_ABIS
+        .set(abis)
+        .map_err(|_| "User ABIs must be loaded once, before the first decode")?;
+    Ok(count)
+}
+
+fn parse_user_abi(path: &Path) -> Result<Contract, Box<dyn Error>> {
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let abi = match json {
+        serde_json::Value::Object(mut artifact) => {
+            artifact.remove("abi").ok_or("missing `abi` field")?
+        }
+        abi => abi,
     };
+    Ok(serde_json::from_value(abi)?)
+}
 
-    pub static ref UNISWAP_V2_ABI: String = fs::read_to_string("./uniswap/UniswapV2Router.json")
-        .expect("Unable to read Uniswap V2 Router ABI file");
+/// Labels of the ABIs added with `load_user_abis`.
+pub fn user_abi_labels() -> Vec<&'static str> {
+    USER_ABIS
+        .get()
+        .map(|abis| abis.iter().map(|abi| abi.label).collect())
+        .unwrap_or_default()
+}
 
-    pub static ref UNISWAP_V3_ABI: String = fs::read_to_string("./uniswap/UniswapV3Router.json")
-        .expect("Unable to read Uniswap V3 Router ABI file");
+/// The parsed ABI registered under `label`.
+pub fn parsed_abi(label: &str) -> Option<&'static Contract> {
+    PARSED_ABIS
+        .iter()
+        .find(|abi| abi.label == label)
+        .map(|abi| &abi.contract)
+}
+
+#[derive(Debug, Clone)]
+pub struct RegisteredFunction {
+    /// Label of the ABI the function was taken from.
+    pub abi: &'static str,
+    pub protocol: Protocol,
+    pub selector: [u8; 4],
+    pub signature: String,
+    pub function: Function,
+}
+
+/// Two different signatures hashing to the same selector.
+#[derive(Debug, Clone)]
+pub struct SelectorCollision {
+    pub selector: [u8; 4],
+    pub kept: String,
+    pub dropped: String,
+    pub abi: &'static str,
+}
+
+#[derive(Debug, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<[u8; 4], RegisteredFunction>,
+    by_abi: HashMap<&'static str, HashMap<[u8; 4], RegisteredFunction>>,
+    collisions: Vec<SelectorCollision>,
+}
+
+impl FunctionRegistry {
+    pub fn build(abis: &[ParsedAbi]) -> Self {
+        let mut registry = FunctionRegistry::default();
+
+        for abi in abis {
+            registry.add_contract(abi.label, abi.protocol, &abi.contract);
+        }
+
+        registry
+    }
+
+    pub fn add_contract(&mut self, abi: &'static str, protocol: Protocol, contract: &Contract) {
+        for function in contract.functions() {
+            let selector = function.short_signature();
+            let signature = canonical_signature(function);
+            let registered = RegisteredFunction {
+                abi,
+                protocol,
+                selector,
+                signature: signature.clone(),
+                function: function.clone(),
+            };
+
+            self.by_abi
+                .entry(abi)
+                .or_default()
+                .insert(selector, registered.clone());
+
+            match self.functions.get(&selector) {
+                Some(existing) if existing.signature == signature => {}
+                Some(existing) => self.collisions.push(SelectorCollision {
+                    selector,
+                    kept: format!("{} ({})", existing.signature, existing.abi),
+                    dropped: signature,
+                    abi,
+                }),
+                None => {
+                    self.functions.insert(selector, registered);
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, selector: &[u8; 4]) -> Option<&RegisteredFunction> {
+        self.functions.get(selector)
+    }
+
+    /// Looks `selector` up in a single ABI, ignoring the others.
+    pub fn get_in(&self, abi: &str, selector: &[u8; 4]) -> Option<&RegisteredFunction> {
+        self.by_abi
+            .get(abi)
+            .and_then(|functions| functions.get(selector))
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    pub fn collisions(&self) -> &[SelectorCollision] {
+        &self.collisions
+    }
+}
+
+/// Builds the registry and logs any selector collisions. Meant to run once at
+/// startup so a broken ABI shows up before the first transaction arrives.
+pub fn report_selector_registry() {
+    info!("Registered {} function selectors", FUNCTION_REGISTRY.len());
+
+    for collision in FUNCTION_REGISTRY.collisions() {
+        warn!(
+            "Selector collision 0x{}: keeping {}, dropping {} ({})",
+            hex::encode(collision.selector),
+            collision.kept,
+            collision.dropped,
+            collision.abi
+        );
+    }
 }
 
 pub fn keccak256(input: &str) -> [u8; 4] {
@@ -50,3 +265,15 @@ pub fn keccak256(input: &str) -> [u8; 4] {
     hasher.finalize(&mut output);
     [output[0], output[1], output[2], output[3]]
 }
+
+/// `name(type,...)` without the output list that `Function::signature` appends.
+pub fn canonical_signature(function: &Function) -> String {
+    let inputs = function
+        .inputs
+        .iter()
+        .map(|param| param.kind.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!("{}({})", function.name, inputs)
+}
@@ -0,0 +1,241 @@
+use ethabi::{Address, ParamType, Token};
+use ethers::types::{I256, U256};
+
+use super::logger::token_to_string;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedParam {
+    pub name: String,
+    pub kind: ParamType,
+    pub value: Token,
+}
+
+/// One pool hop of a swap route, always in execution order. `fee` is the V3
+/// fee tier in hundredths of a bip (3000 = 0.3%).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hop {
+    pub token_in: Address,
+    pub fee: Option<u32>,
+    pub token_out: Address,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    UniswapV2,
+    UniswapV3,
+    UniswapUniversalRouter,
+    Dodo,
+    /// Decoded with a user-supplied ABI, named after its file.
+    Custom(&'static str),
+}
+
+impl Protocol {
+    /// Short name used in config, e.g. `uniswap-v3`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Protocol::UniswapV2 => "uniswap-v2",
+            Protocol::UniswapV3 => "uniswap-v3",
+            Protocol::UniswapUniversalRouter => "uniswap-universal-router",
+            Protocol::Dodo => "dodo",
+            Protocol::Custom(name) => name,
+        }
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Protocol::UniswapV2 => "Uniswap V2",
+            Protocol::UniswapV3 => "Uniswap V3",
+            Protocol::UniswapUniversalRouter => "Uniswap Universal Router",
+            Protocol::Dodo => "DODO",
+            Protocol::Custom(name) => name,
+        };
+        f.write_str(name)
+    }
+}
+
+/// How the decoder picked the ABI for a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    /// `to` is a known deployment and the selector exists in its ABI.
+    KnownContract,
+    /// `to` is unknown (or didn't match); the selector alone picked the ABI, so
+    /// this may be an unrelated contract that happens to share it.
+    SelectorOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    /// A regular ABI function call, identified by its 4-byte selector.
+    Function { selector: [u8; 4] },
+    /// A Universal Router command. `allow_revert` mirrors the 0x80 flag bit.
+    Command { opcode: u8, allow_revert: bool },
+}
+
+/// A decoded function call. Calls that wrap other calls (multicall, Universal
+/// Router `execute`) carry the decoded inner calls as `children`, in calldata
+/// order. A child that could not be decoded is still present, with `error`
+/// set and its raw calldata as the only param.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCall {
+    pub function: String,
+    pub signature: String,
+    pub kind: CallKind,
+    pub params: Vec<DecodedParam>,
+    /// Swap route decoded from a packed path argument, empty if the call has none.
+    pub route: Vec<Hop>,
+    pub children: Vec<DecodedCall>,
+    pub error: Option<String>,
+}
+
+impl DecodedCall {
+    pub fn selector(&self) -> Option<[u8; 4]> {
+        match self.kind {
+            CallKind::Function { selector } => Some(selector),
+            CallKind::Command { .. } => None,
+        }
+    }
+
+    pub fn selector_hex(&self) -> Option<String> {
+        self.selector()
+            .map(|selector| format!("0x{}", hex::encode(selector)))
+    }
+
+    pub fn param(&self, name: &str) -> Option<&Token> {
+        self.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| &param.value)
+    }
+}
+
+/// A decoded top-level transaction: the call tree plus what we know about the
+/// contract it was sent to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTransaction {
+    pub to: Address,
+    pub protocol: Protocol,
+    pub confidence: Confidence,
+    pub call: DecodedCall,
+}
+
+/// Why a call reverted, decoded from its revert data.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RevertReason {
+    /// Reverted without data (`revert()`, out of gas, ...).
+    Empty,
+    /// `Error(string)`, i.e. `require(cond, "message")`.
+    Error(String),
+    /// `Panic(uint256)` raised by the compiler, e.g. on overflow.
+    Panic { code: U256, description: String },
+    /// A custom error from one of the loaded ABIs.
+    Custom {
+        name: String,
+        signature: String,
+        params: Vec<DecodedParam>,
+    },
+    /// Universal Router `ExecutionFailed(uint256,bytes)`, with the inner
+    /// revert of the failing command decoded.
+    ExecutionFailed {
+        command_index: U256,
+        reason: Box<RevertReason>,
+    },
+    /// Data we have no matching error selector for.
+    Unknown(Vec<u8>),
+}
+
+impl std::fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RevertReason::Empty => write!(f, "reverted without data"),
+            RevertReason::Error(message) => write!(f, "Error({:?})", message),
+            RevertReason::Panic { code, description } => {
+                write!(f, "Panic(0x{:02x}): {}", code, description)
+            }
+            RevertReason::Custom { name, params, .. } => {
+                let params = params
+                    .iter()
+                    .map(|param| format!("{}: {}", param.name, token_to_string(&param.value)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{}({})", name, params)
+            }
+            RevertReason::ExecutionFailed {
+                command_index,
+                reason,
+            } => write!(f, "ExecutionFailed(command {}): {}", command_index, reason),
+            RevertReason::Unknown(data) => write!(f, "unknown revert 0x{}", hex::encode(data)),
+        }
+    }
+}
+
+/// A log from one of the pool, token or router events we know about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedEvent {
+    UniswapV2Swap {
+        sender: Address,
+        amount0_in: U256,
+        amount1_in: U256,
+        amount0_out: U256,
+        amount1_out: U256,
+        to: Address,
+    },
+    UniswapV2Sync {
+        reserve0: U256,
+        reserve1: U256,
+    },
+    /// Amounts are from the pool's point of view: positive means the pool
+    /// received that token.
+    UniswapV3Swap {
+        sender: Address,
+        recipient: Address,
+        amount0: I256,
+        amount1: I256,
+        sqrt_price_x96: U256,
+        liquidity: U256,
+        tick: i32,
+    },
+    Transfer {
+        from: Address,
+        to: Address,
+        value: U256,
+    },
+    Approval {
+        owner: Address,
+        spender: Address,
+        value: U256,
+    },
+    Deposit {
+        dst: Address,
+        wad: U256,
+    },
+    Withdrawal {
+        src: Address,
+        wad: U256,
+    },
+    DodoOrderHistory {
+        from_token: Address,
+        to_token: Address,
+        sender: Address,
+        from_amount: U256,
+        return_amount: U256,
+    },
+    DodoSwap {
+        from_token: Address,
+        to_token: Address,
+        from_amount: U256,
+        to_amount: U256,
+        trader: Address,
+        receiver: Address,
+    },
+}
+
+/// A decoded receipt log. `address` is the emitting contract: the pair or
+/// pool for swaps, the token for transfers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedLog {
+    pub address: Address,
+    pub log_index: Option<U256>,
+    pub event: DecodedEvent,
+}
@@ -0,0 +1,29 @@
+use ethers::types::{Address, U256};
+
+use super::decoded::{Hop, Protocol};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAmounts {
+    /// Spend exactly `amount_in`, revert unless at least `min_out` comes back.
+    ExactIn { amount_in: U256, min_out: U256 },
+    /// Receive exactly `amount_out`, revert if it costs more than `max_in`.
+    ExactOut { amount_out: U256, max_in: U256 },
+}
+
+/// What a swap is trying to do, independent of which router encoded it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapIntent {
+    pub protocol: Protocol,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amounts: SwapAmounts,
+    /// Where the output goes. Routers use sentinels here: `0x…01` is the
+    /// sender and `0x…02` the router itself (e.g. before an unwrap). The first
+    /// resolves to the transaction's `from`, the second to the unwrap
+    /// recipient when the call tree has one.
+    pub recipient: Option<Address>,
+    pub deadline: Option<U256>,
+    pub hops: Vec<Hop>,
+    /// ETH is paid in with the transaction or unwrapped on the way out.
+    pub uses_native_eth: bool,
+}
@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256, U256};
+
+/// How a tracked pending transaction left the mempool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Mined at `position` in `block`, `latency` after it was first seen.
+    Included {
+        block: u64,
+        position: usize,
+        latency: Duration,
+    },
+    /// Another transaction took the same sender and nonce.
+    Replaced { by: H256 },
+    /// Neither mined nor replaced within the timeout.
+    Dropped { after: Duration },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    pub hash: H256,
+    pub sender: Address,
+    pub nonce: U256,
+    pub first_seen: DateTime<Utc>,
+    pub outcome: Outcome,
+}
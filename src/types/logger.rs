@@ -1,6 +1,19 @@
+use std::collections::HashMap;
+
 use chrono::Local;
 use colored::Colorize;
-use ethabi::Token;
+use ethabi::{ParamType, Token};
+use ethers::types::{Address, H256, I256, U256};
+
+use super::{
+    decoded::{
+        CallKind, Confidence, DecodedCall, DecodedParam, DecodedTransaction, Hop, RevertReason,
+    },
+    intent::{SwapAmounts, SwapIntent},
+    lifecycle::{Outcome, Resolution},
+    replacement::Replacement,
+    token::{format_units, TokenMetadata},
+};
 
 pub fn token_to_string(token: &Token) -> String {
     match token {
@@ -36,54 +49,248 @@ pub fn token_to_string(token: &Token) -> String {
     }
 }
 
-pub fn log_decoded_input(function_name: &str, input_data: &str) {
+/// Renders hops as `tokenA -[0.30%]-> tokenB -[0.05%]-> tokenC`.
+pub fn route_to_string(route: &[Hop]) -> String {
+    let mut out = match route.first() {
+        Some(hop) => format!("{:?}", hop.token_in),
+        None => return String::new(),
+    };
+
+    for hop in route {
+        let fee = match hop.fee {
+            Some(fee) => format!("{:.2}%", fee as f64 / 10_000.0),
+            None => "v2".to_string(),
+        };
+        out.push_str(&format!(" -[{}]-> {:?}", fee, hop.token_out));
+    }
+
+    out
+}
+
+/// `chain` is named in the header when set, for runs across several chains.
+pub fn log_decoded_transaction(decoded: &DecodedTransaction, chain: Option<&str>) {
+    let timestamp = Local::now().format("%H:%M:%S%.3f").to_string();
+    let chain = chain
+        .map(|chain| format!(" [{}]", chain))
+        .unwrap_or_default();
+    let header = format!(
+        "{} ⮞ Decoded input for {} on {}{} ({:?}):",
+        timestamp, decoded.call.function, decoded.protocol, chain, decoded.to
+    )
+    .bright_blue();
+
+    match decoded.confidence {
+        Confidence::KnownContract => println!("\n{}", header),
+        Confidence::SelectorOnly => {
+            println!("\n{} {}", header, "[selector match only]".bright_black())
+        }
+    }
+    print_call(&decoded.call, 1);
+    println!();
+}
+
+pub fn log_decoded_call(call: &DecodedCall) {
     let timestamp = Local::now().format("%H:%M:%S%.3f").to_string();
-    let header = format!("{} ⮞ Decoded input for {}:", timestamp, function_name).bright_blue();
+    let header = format!("{} ⮞ Decoded input for {}:", timestamp, call.function).bright_blue();
 
     println!("\n{}", header);
+    print_call(call, 1);
+    println!();
+}
+
+/// `1.5 WETH → at least 4,210.33 USDC`, falling back to raw amounts and
+/// addresses for tokens without metadata.
+pub fn intent_summary(
+    intent: &SwapIntent,
+    token_in: Option<&TokenMetadata>,
+    token_out: Option<&TokenMetadata>,
+) -> String {
+    let amount = |amount: U256, token: &Address, metadata: Option<&TokenMetadata>| match metadata {
+        Some(metadata) => metadata.format_amount(amount),
+        None => format!("{} {:?}", amount, token),
+    };
+
+    match intent.amounts {
+        SwapAmounts::ExactIn { amount_in, min_out } => format!(
+            "{} → at least {}",
+            amount(amount_in, &intent.token_in, token_in),
+            amount(min_out, &intent.token_out, token_out)
+        ),
+        SwapAmounts::ExactOut { amount_out, max_in } => format!(
+            "at most {} → {}",
+            amount(max_in, &intent.token_in, token_in),
+            amount(amount_out, &intent.token_out, token_out)
+        ),
+    }
+}
+
+pub fn log_swap_intent(
+    intent: &SwapIntent,
+    token_in: Option<&TokenMetadata>,
+    token_out: Option<&TokenMetadata>,
+) {
+    println!(
+        "  {} {} on {}",
+        "intent".bright_blue(),
+        intent_summary(intent, token_in, token_out),
+        intent.protocol
+    );
+
+    if let Some(recipient) = intent.recipient {
+        println!("    {} {:?}", "recipient".yellow(), recipient);
+    }
+    if let Some(deadline) = intent.deadline {
+        println!("    {} {}", "deadline".yellow(), deadline);
+    }
+    if !intent.hops.is_empty() {
+        println!(
+            "    {} {}",
+            "route".yellow(),
+            route_to_string(&intent.hops).bright_green()
+        );
+    }
+    if intent.uses_native_eth {
+        println!("    {}", "native ETH".bright_black());
+    }
+}
+
+pub fn log_replacement(replacement: &Replacement) {
+    let timestamp = replacement.at.with_timezone(&Local).format("%H:%M:%S%.3f");
+    println!(
+        "\n{}",
+        format!(
+            "{} ⮞ Replacement of nonce {} from {:?}:",
+            timestamp, replacement.nonce, replacement.sender
+        )
+        .bright_magenta()
+    );
+    println!(
+        "  {} {:?} ({} gwei)",
+        "replaced".yellow(),
+        replacement.replaced,
+        format_units(replacement.replaced_gas_price, 9)
+    );
+    println!(
+        "  {} {:?} ({} gwei)",
+        "by".yellow(),
+        replacement.replacement,
+        format_units(replacement.gas_price, 9)
+    );
+}
 
-    if function_name == "multicall" {
-        println!("  {}", "multicall".yellow());
-        let calls: Vec<&str> = input_data.split("], [").collect();
-        for (i, call) in calls.iter().enumerate() {
-            println!("    Call {}:", i + 1);
-            let parts: Vec<&str> = call
-                .trim_matches(|c| c == '[' || c == ']' || c == '(' || c == ')')
-                .split(", ")
-                .collect();
-            if parts.len() >= 2 {
-                println!("      {}: {}", "target".yellow(), parts[0].bright_green());
-                println!(
-                    "      {}: {}",
-                    "callData".yellow(),
-                    format!("0x{}...", &parts[1][2..8]).bright_green()
-                );
+pub fn log_resolution(resolution: &Resolution) {
+    let timestamp = Local::now().format("%H:%M:%S%.3f");
+    let outcome = match &resolution.outcome {
+        Outcome::Included {
+            block,
+            position,
+            latency,
+        } => format!(
+            "included in block {} at position {}, {}ms after first seen",
+            block,
+            position,
+            latency.as_millis()
+        )
+        .bright_green(),
+        Outcome::Replaced { by } => format!("replaced by {:?}", by).yellow(),
+        Outcome::Dropped { after } => format!("dropped after {}s pending", after.as_secs()).red(),
+    };
+
+    println!("{} ⮞ {:?} {}", timestamp, resolution.hash, outcome);
+}
+
+/// Net token movement of the sender of a mined transaction, in raw units.
+pub fn log_execution(hash: H256, flows: &HashMap<Address, I256>) {
+    let timestamp = Local::now().format("%H:%M:%S%.3f");
+    println!("{} ⮞ {:?} {}", timestamp, hash, "executed".bright_green());
+
+    for (token, amount) in flows.iter().filter(|(_, amount)| !amount.is_zero()) {
+        let amount = if amount.is_positive() {
+            format!("+{}", amount).bright_green()
+        } else {
+            amount.to_string().red()
+        };
+        println!("  {} {:?} {}", "token".yellow(), token, amount);
+    }
+}
+
+pub fn log_revert(hash: H256, reason: Option<&RevertReason>) {
+    let timestamp = Local::now().format("%H:%M:%S%.3f");
+    let reason = reason.map_or_else(
+        || "revert reason unavailable".to_string(),
+        |r| r.to_string(),
+    );
+    println!(
+        "{} ⮞ {:?} {} {}",
+        timestamp,
+        hash,
+        "reverted:".red(),
+        reason.red()
+    );
+}
+
+fn print_call(call: &DecodedCall, depth: usize) {
+    let indent = "  ".repeat(depth);
+
+    match call.kind {
+        CallKind::Command {
+            allow_revert: true, ..
+        } => println!(
+            "{}{} {}",
+            indent,
+            call.signature.yellow(),
+            "(allow revert)".bright_black()
+        ),
+        _ => println!("{}{}", indent, call.signature.yellow()),
+    }
+
+    if let Some(error) = &call.error {
+        println!("{}  {} {}", indent, "error".red(), error.red());
+    }
+
+    for param in &call.params {
+        print_param(param, depth + 1);
+    }
+
+    if !call.route.is_empty() {
+        println!(
+            "{}  {} {}",
+            indent,
+            "route".yellow(),
+            route_to_string(&call.route).bright_green()
+        );
+    }
+
+    for (i, child) in call.children.iter().enumerate() {
+        println!("{}Call {}:", indent, i + 1);
+        print_call(child, depth + 1);
+    }
+}
+
+fn print_param(param: &DecodedParam, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let label = format!("{} ({})", param.name, param.kind);
+
+    match (&param.kind, &param.value) {
+        (ParamType::Tuple(types), Token::Tuple(items)) => {
+            println!("{}{}", indent, label.yellow());
+            for (item_type, item_value) in types.iter().zip(items) {
+                let formatted_item_type = format!("  {:<15}", item_type.to_string()).yellow();
+                let formatted_item_value = token_to_string(item_value).bright_green();
+                println!("{}{} {}", indent, formatted_item_type, formatted_item_value);
             }
         }
-    } else if let Some((param_type, value)) = input_data.split_once(' ') {
-        let formatted_type = format!("{}", param_type).yellow();
-        println!("  {}", formatted_type);
-
-        if param_type.starts_with("(") {
-            // For tuples, split the content and display each item on a new line
-            let items = value.trim_matches(|c| c == '(' || c == ')').split(',');
-            let types = param_type.trim_matches(|c| c == '(' || c == ')').split(',');
-
-            for (item_type, item_value) in types.zip(items) {
-                let formatted_item_type = format!("    {:<15}", item_type.trim()).yellow();
-                let formatted_item_value = item_value.trim().bright_green();
-                println!("  {} {}", formatted_item_type, formatted_item_value);
-            }
-        } else if param_type.starts_with("bytes") {
-            let formatted_value = format!("0x{}...", &value[2..8]).bright_green();
-            println!("    {}", formatted_value);
-        } else {
-            let formatted_value = value.bright_green();
-            println!("    {}", formatted_value);
+        (ParamType::Array(inner), Token::Array(items)) if **inner == ParamType::Bytes => {
+            let formatted_value = format!("[{} entries]", items.len()).bright_green();
+            println!("{}{} {}", indent, label.yellow(), formatted_value);
+        }
+        (_, Token::Bytes(bytes)) if bytes.len() > 3 => {
+            let formatted_value = format!("0x{}...", hex::encode(&bytes[..3])).bright_green();
+            println!("{}{} {}", indent, label.yellow(), formatted_value);
+        }
+        _ => {
+            let formatted_value = token_to_string(&param.value).bright_green();
+            println!("{}{} {}", indent, label.yellow(), formatted_value);
         }
-    } else {
-        println!("  {}", input_data.bright_green());
     }
-
-    println!();
 }
@@ -1,2 +1,8 @@
+pub mod decoded;
+pub mod intent;
+pub mod lifecycle;
 pub mod logger;
+pub mod recorded;
+pub mod replacement;
 pub mod settings;
+pub mod token;
@@ -0,0 +1,45 @@
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use ethers::{
+    types::{Bytes, Transaction, H256},
+    utils::rlp,
+};
+use serde::{Deserialize, Serialize};
+
+/// One line of a recorded pending transaction file: the transaction as raw
+/// signed RLP (`raw`) or as an RPC transaction object (`tx`), with when and
+/// where it was first seen. A line with only `hash` is a pending hash as it
+/// arrived, before its body was fetched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedTransaction {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<H256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Bytes>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx: Option<Transaction>,
+    pub first_seen: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl RecordedTransaction {
+    /// A pending hash without its body.
+    pub fn is_hash_only(&self) -> bool {
+        self.raw.is_none() && self.tx.is_none() && self.hash.is_some()
+    }
+
+    /// The recorded transaction, decoding `raw` and recovering its sender if
+    /// there's no `tx`.
+    pub fn transaction(&self) -> Result<Transaction, Box<dyn Error + Send + Sync>> {
+        if let Some(tx) = &self.tx {
+            return Ok(tx.clone());
+        }
+        let raw = self.raw.as_ref().ok_or("neither `raw` nor `tx` is set")?;
+
+        let mut tx: Transaction = rlp::decode(raw)?;
+        tx.recover_from_mut()?;
+        Ok(tx)
+    }
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256, U256};
+
+/// A pending transaction that reuses the sender and nonce of an earlier one,
+/// usually to speed it up or cancel it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    pub sender: Address,
+    pub nonce: U256,
+    pub replaced: H256,
+    pub replaced_gas_price: U256,
+    pub replacement: H256,
+    pub gas_price: U256,
+    pub at: DateTime<Utc>,
+}
@@ -2,16 +2,238 @@ use config::{Config, ConfigError, File};
 use serde::Serialize;
 use serde_derive::Deserialize;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct Connection {
+    /// Chain profile: `mainnet`, `arbitrum`, `base`, `optimism`, `bsc` or
+    /// `polygon`.
+    #[serde(default = "default_chain")]
+    pub chain: String,
     pub ethereum_rpc_url: String,
     pub wss_node_endpoint: String,
+    /// More WS nodes to take pending transactions from, merged with
+    /// `wss_node_endpoint` and deduplicated by hash.
+    #[serde(default)]
+    pub extra_wss_endpoints: Vec<String>,
+    /// Unix socket of a co-located node, given as an absolute path or one
+    /// ending in `.ipc`. When set, it replaces `wss_node_endpoint` for
+    /// subscriptions and `ethereum_rpc_url` for lookups.
+    #[serde(default)]
+    pub ipc_path: Option<String>,
+    #[serde(default)]
     pub flashbots_url: String,
+    #[serde(default)]
     builders_url: Vec<String>,
+    #[serde(default)]
+    pub pending_transactions: PendingTransactions,
+    /// First reconnect delay; doubles on every failed attempt.
+    #[serde(default = "default_reconnect_initial_ms")]
+    pub reconnect_initial_ms: u64,
+    #[serde(default = "default_reconnect_max_ms")]
+    pub reconnect_max_ms: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Connection {
+    /// `ipc_path` or else `wss_node_endpoint`, followed by
+    /// `extra_wss_endpoints`, without repeats.
+    pub fn pending_endpoints(&self) -> Vec<String> {
+        let primary = self.ipc_path.as_ref().unwrap_or(&self.wss_node_endpoint);
+        let mut endpoints = vec![primary.clone()];
+        for endpoint in &self.extra_wss_endpoints {
+            if !endpoints.contains(endpoint) {
+                endpoints.push(endpoint.clone());
+            }
+        }
+        endpoints
+    }
+}
+
+fn default_chain() -> String {
+    "mainnet".to_string()
+}
+
+fn default_reconnect_initial_ms() -> u64 {
+    500
+}
+
+fn default_reconnect_max_ms() -> u64 {
+    30_000
+}
+
+/// How pending transactions are received from `wss_node_endpoint`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PendingTransactions {
+    /// `eth_subscribe("newPendingTransactions", true)`: full bodies pushed over
+    /// the socket. Falls back to `hashes` if the node rejects it.
+    #[default]
+    Full,
+    /// Pending hashes, each fetched with `eth_getTransactionByHash` over HTTP.
+    Hashes,
+}
+
+/// Listener worker pool and its queue of pending transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct Workers {
+    /// Transactions fetched and decoded at the same time.
+    pub concurrency: usize,
+    /// Transactions waiting for a worker before `drop_policy` kicks in.
+    pub queue_size: usize,
+    pub drop_policy: DropPolicy,
+}
+
+impl Default for Workers {
+    fn default() -> Self {
+        Workers {
+            concurrency: 64,
+            queue_size: 10_000,
+            drop_policy: DropPolicy::Oldest,
+        }
+    }
+}
+
+/// Which queued transaction is discarded when the worker queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropPolicy {
+    #[default]
+    Oldest,
+    Newest,
+    /// Lowest gas price (max fee for EIP-1559 transactions). Hashes that
+    /// haven't been fetched yet have no price and go first, oldest first, so
+    /// with `pending_transactions = "hashes"` this behaves like `oldest`.
+    LowestGasPrice,
+}
+
+/// Limits of the caches that deduplicate pending hashes and track
+/// `(sender, nonce)` for replacements.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct Dedup {
+    pub ttl_secs: u64,
+    pub capacity: usize,
+}
+
+impl Default for Dedup {
+    fn default() -> Self {
+        Dedup {
+            ttl_secs: 600,
+            capacity: 200_000,
+        }
+    }
+}
+
+/// Following shown transactions until they're mined, replaced or dropped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct Lifecycle {
+    pub enabled: bool,
+    /// Still pending after this long counts as dropped.
+    pub drop_timeout_secs: u64,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Lifecycle {
+            enabled: true,
+            drop_timeout_secs: 600,
+        }
+    }
+}
+
+/// Replaying recorded pending transactions instead of listening to a node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct Replay {
+    /// JSONL file of recorded transactions, optionally gzipped, or a
+    /// directory of them such as `[capture] directory`. Replays it when set.
+    pub file: Option<String>,
+    pub pacing: Pacing,
+    /// Don't connect to `[connection]`: token metadata only comes from the
+    /// cache, so a replay only depends on the recording and the cache file.
+    pub offline: bool,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Replay {
+            file: None,
+            pacing: Pacing::default(),
+            offline: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pacing {
+    /// Keep the recorded gaps between first-seen timestamps.
+    #[default]
+    Realtime,
+    /// One after the other, as fast as they decode.
+    Asap,
+}
+
+/// Recording every received pending transaction to disk for later replay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct Capture {
+    /// Where capture files are written. Captures when set.
+    pub directory: Option<String>,
+    /// Start a new file after this much uncompressed data...
+    pub rotate_mb: u64,
+    /// ...or after this long, whichever comes first.
+    pub rotate_minutes: u64,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Capture {
+            directory: None,
+            rotate_mb: 256,
+            rotate_minutes: 60,
+        }
+    }
+}
+
+/// Which transactions are decoded and shown. Empty lists and unset values
+/// don't filter anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct Filter {
+    pub to: Vec<String>,
+    pub from: Vec<String>,
+    /// Top-level 4-byte selectors, e.g. `"0x3593564c"`.
+    pub selectors: Vec<String>,
+    /// Protocol keys: `uniswap-v2`, `uniswap-v3`, `uniswap-universal-router`,
+    /// `dodo`, or the file name of a user ABI.
+    pub protocols: Vec<String>,
+    pub min_value_eth: Option<f64>,
+    pub min_gas_price_gwei: Option<f64>,
+    pub max_gas_price_gwei: Option<f64>,
+    pub min_notional: Option<Notional>,
+    /// Only keep swaps touching at least one of these tokens.
+    pub token_allow: Vec<String>,
+    /// Drop swaps routed through any of these tokens.
+    pub token_deny: Vec<String>,
+}
+
+/// A swap size threshold in units of `token`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct Notional {
+    pub token: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct Contract {
     pub address: String,
@@ -20,7 +242,7 @@ pub struct Contract {
     pub weth: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct Sniper {
     pub private_keys: Vec<String>,
@@ -28,7 +250,7 @@ pub struct Sniper {
     pub max_limit: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct Bundle {
     pub bundler_key: String,
@@ -38,13 +260,70 @@ pub struct Bundle {
     pub delay_s: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct Tokens {
+    /// JSON file token symbols and decimals are cached in between runs.
+    pub cache_path: String,
+}
+
+impl Tokens {
+    /// `cache_path` for mainnet, `token_cache.<chain>.json` alongside it for
+    /// other chains, since the same address can be a different token there.
+    pub fn cache_path_for(&self, chain: &str) -> String {
+        if chain == "mainnet" {
+            return self.cache_path.clone();
+        }
+        match self.cache_path.rsplit_once('.') {
+            Some((stem, extension)) => format!("{}.{}.{}", stem, chain, extension),
+            None => format!("{}.{}", self.cache_path, chain),
+        }
+    }
+}
+
+impl Default for Tokens {
+    fn default() -> Self {
+        Tokens {
+            cache_path: "token_cache.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct Abis {
+    /// Directory of extra `*.json` ABIs to decode with, on top of the bundled
+    /// ones.
+    pub directory: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct Settings {
     pub connection: Connection,
     pub contract: Contract,
     pub sniper: Sniper,
     pub bundle: Bundle,
+    #[serde(default)]
+    pub tokens: Tokens,
+    #[serde(default)]
+    pub abis: Abis,
+    #[serde(default)]
+    pub workers: Workers,
+    #[serde(default)]
+    pub filter: Filter,
+    #[serde(default)]
+    pub dedup: Dedup,
+    #[serde(default)]
+    pub lifecycle: Lifecycle,
+    #[serde(default)]
+    pub replay: Replay,
+    #[serde(default)]
+    pub capture: Capture,
+    /// More chains to listen to alongside `[connection]`, each with its own
+    /// connection. `[contract]` only applies to `[connection]`'s chain.
+    #[serde(default)]
+    pub chains: Vec<Connection>,
 }
 
 impl std::fmt::Debug for Sniper {
@@ -59,6 +338,24 @@ impl std::fmt::Debug for Sniper {
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 impl Settings {
+    /// One copy of the settings per chain to listen to: these, then one for
+    /// each of `chains` with its connection swapped in. Extra chains capture
+    /// into a subdirectory named after the chain.
+    pub fn per_chain(&self) -> Vec<Settings> {
+        let mut settings = vec![self.clone()];
+        for connection in &self.chains {
+            let mut chain = self.clone();
+            chain.connection = connection.clone();
+            chain.capture.directory = self
+                .capture
+                .directory
+                .as_ref()
+                .map(|directory| format!("{}/{}", directory, connection.chain));
+            settings.push(chain);
+        }
+        settings
+    }
+
     pub fn new() -> Result<Self, ConfigError> {
         let s = Config::builder()
             .add_source(File::with_name("config"))
@@ -0,0 +1,46 @@
+use ethers::types::U256;
+use serde::Serialize;
+use serde_derive::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+}
+
+impl TokenMetadata {
+    /// `amount` in whole tokens with its symbol, e.g. `4,210.33 USDC`.
+    pub fn format_amount(&self, amount: U256) -> String {
+        format!("{} {}", format_units(amount, self.decimals), self.symbol)
+    }
+}
+
+/// `amount` scaled down by `decimals`, with thousands separators and at most
+/// four fractional digits (truncated, trailing zeros dropped).
+pub fn format_units(amount: U256, decimals: u8) -> String {
+    let digits = amount.to_string();
+    let decimals = decimals as usize;
+
+    let (whole, fraction) = if digits.len() > decimals {
+        digits.split_at(digits.len() - decimals)
+    } else {
+        ("0", digits.as_str())
+    };
+    let fraction = format!("{:0>width$}", fraction, width = decimals);
+    let fraction = fraction[..decimals.min(4)].trim_end_matches('0');
+
+    let mut grouped = String::new();
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    if fraction.is_empty() {
+        grouped
+    } else {
+        format!("{}.{}", grouped, fraction)
+    }
+}
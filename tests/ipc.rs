@@ -0,0 +1,210 @@
+//! Lookups over IPC against a stand-in node on a local Unix socket.
+#![cfg(unix)]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use ethers::types::{H256, U256};
+use ethers_providers::{Middleware, Provider};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream},
+    task::{JoinHandle, JoinSet},
+};
+use uniswap_v3_mev::{
+    mempool::{
+        connection::RpcClient, contracts::ContractRegistry, feed::Feed,
+        listener::mempool_listener_with_feed, protocol::DecoderRegistry,
+    },
+    types::settings::{Connection, Settings},
+};
+
+const SUBSCRIPTION: &str = "0x9cef478923ff08bf67fde6c64013158d";
+const PENDING_HASH: H256 = H256::repeat_byte(0x44);
+
+/// Answers `eth_chainId`, `eth_getTransactionByHash` and `eth_subscribe`
+/// until aborted, which closes every connection like a node going down.
+/// Every subscription is sent one pending transaction, `PENDING_HASH`.
+fn stand_in(path: PathBuf) -> JoinHandle<()> {
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    tokio::spawn(async move {
+        let mut connections = JoinSet::new();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            connections.spawn(serve(stream));
+        }
+    })
+}
+
+async fn serve(mut stream: UnixStream) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let read = match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => read,
+        };
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let mut requests = serde_json::Deserializer::from_slice(&buffer).into_iter::<Value>();
+        let mut responses = Vec::new();
+        let mut subscribed = false;
+        while let Some(Ok(request)) = requests.next() {
+            subscribed |= request["method"] == "eth_subscribe";
+            responses.push(respond(&request));
+        }
+        let consumed = requests.byte_offset();
+        buffer.drain(..consumed);
+
+        for response in responses {
+            write(&mut stream, &response).await;
+        }
+
+        // The client only routes notifications once it has read the
+        // subscription id, so give it a moment and repeat the push.
+        if subscribed {
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "eth_subscription",
+                "params": {
+                    "subscription": SUBSCRIPTION,
+                    "result": transaction(json!(PENDING_HASH)),
+                },
+            });
+            for _ in 0..3 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                write(&mut stream, &notification).await;
+            }
+        }
+    }
+}
+
+async fn write(stream: &mut UnixStream, message: &Value) {
+    stream
+        .write_all(message.to_string().as_bytes())
+        .await
+        .unwrap();
+}
+
+fn respond(request: &Value) -> Value {
+    let result = match request["method"].as_str() {
+        Some("eth_chainId") => json!("0x1"),
+        Some("eth_getTransactionByHash") => transaction(request["params"][0].clone()),
+        Some("eth_subscribe") => json!(SUBSCRIPTION),
+        _ => Value::Null,
+    };
+    json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+}
+
+fn transaction(hash: Value) -> Value {
+    json!({
+        "hash": hash,
+        "nonce": "0x7",
+        "blockHash": null,
+        "blockNumber": null,
+        "transactionIndex": null,
+        "from": "0x2222222222222222222222222222222222222222",
+        "to": "0x3333333333333333333333333333333333333333",
+        "value": "0x0",
+        "gasPrice": "0x3b9aca00",
+        "gas": "0x5208",
+        "input": "0x",
+        "v": "0x1b",
+        "r": "0x1",
+        "s": "0x1",
+        "type": "0x0"
+    })
+}
+
+/// `config.toml`'s connection, pointed at the stand-in.
+fn connection(path: &Path) -> Connection {
+    let mut connection = Settings::new().unwrap().connection;
+    connection.ipc_path = Some(path.display().to_string());
+    connection.reconnect_initial_ms = 10;
+    connection.reconnect_max_ms = 50;
+    connection
+}
+
+#[tokio::test]
+async fn lookups_go_over_ipc() {
+    let path = std::env::temp_dir().join(format!("ipc-lookups-{}.ipc", std::process::id()));
+    let node = stand_in(path.clone());
+
+    let provider = Provider::new(RpcClient::connect(&connection(&path)).await.unwrap());
+    assert_eq!(provider.get_chainid().await.unwrap(), U256::one());
+
+    let hash = H256::repeat_byte(0x11);
+    let transaction = provider.get_transaction(hash).await.unwrap().unwrap();
+    assert_eq!(transaction.hash, hash);
+    assert_eq!(transaction.nonce, U256::from(7));
+
+    node.abort();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn lookups_reconnect_after_node_restart() {
+    let path = std::env::temp_dir().join(format!("ipc-restart-{}.ipc", std::process::id()));
+    let node = stand_in(path.clone());
+
+    let provider = Provider::new(RpcClient::connect(&connection(&path)).await.unwrap());
+    assert_eq!(provider.get_chainid().await.unwrap(), U256::one());
+
+    node.abort();
+    let _ = node.await;
+    let _ = std::fs::remove_file(&path);
+    assert!(provider.get_chainid().await.is_err());
+
+    let node = stand_in(path.clone());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(provider.get_chainid().await.unwrap(), U256::one());
+
+    node.abort();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn pending_transactions_arrive_over_ipc() {
+    let path = std::env::temp_dir().join(format!("ipc-pending-{}.ipc", std::process::id()));
+    let node = stand_in(path.clone());
+
+    let mut config = Settings::new().unwrap();
+    config.connection = connection(&path);
+    config.connection.extra_wss_endpoints.clear();
+    config.lifecycle.enabled = false;
+    config.capture.directory = None;
+    config.tokens.cache_path = std::env::temp_dir()
+        .join(format!("ipc-pending-tokens-{}.json", std::process::id()))
+        .display()
+        .to_string();
+
+    let feed = Arc::new(Feed::new(&config.connection.pending_endpoints(), &config.dedup).unwrap());
+    let decoders = DecoderRegistry::with_builtin(ContractRegistry::default());
+    let listener = mempool_listener_with_feed(config, decoders, Arc::clone(&feed));
+
+    let delivered = async {
+        while feed.first_seen(&PENDING_HASH).is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::select! {
+        result = listener => panic!("listener stopped: {:?}", result.err()),
+        _ = tokio::time::timeout(Duration::from_secs(5), delivered) => {}
+    }
+
+    let first_seen = feed
+        .first_seen(&PENDING_HASH)
+        .expect("pending transaction delivered");
+    assert_eq!(&*first_seen.endpoint, path.display().to_string());
+    assert_eq!(feed.endpoints()[0].stats().first, 1);
+
+    node.abort();
+    let _ = std::fs::remove_file(&path);
+}